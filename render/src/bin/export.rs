@@ -0,0 +1,238 @@
+#![forbid(unsafe_code)]
+#![cfg_attr(not(debug_assertions), deny(warnings))] // Forbid warnings in release builds
+#![warn(clippy::all, rust_2018_idioms)]
+
+//! window を開かずに、試合を PNG の連番か animated GIF に書き出す
+//!
+//! ```text
+//! cargo run -p render --release --bin export -- [options]
+//!   --replay <path>       replay (テキスト形式か JSON) を書き出す
+//!   --seed <n>            replay の代わりに seed から試合をする (default: 0)
+//!   --player1 <strategy>  solver | beam | mcts | wait (default: solver)
+//!   --player2 <strategy>  solver | beam | mcts | wait (default: solver)
+//!   --params <path>       solver のパラメータ (TOML か JSON、default: 環境変数 SOLVER_PARAMS, SOLVER_<名前>)
+//!   --annotation <who>    none | 1 | 2 | both: solver の判断材料を描く player (default: none)
+//!   --from <turn>         書き出す最初の turn (default: 0)
+//!   --to <turn>           書き出す最後の turn (default: 試合の最後)
+//!   --fps <n>             GIF の 1秒あたりの turn 数 (default: 5)
+//!   --out <path>          .gif なら animated GIF、それ以外はディレクトリに frame_0000.png, ... を書く
+//!                         (default: out.gif)
+//! ```
+
+#[cfg(not(target_arch = "wasm32"))]
+fn main() {
+    if let Err(e) = export::run(std::env::args().skip(1).collect()) {
+        eprintln!("error: {}", e);
+        std::process::exit(1);
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+mod export {
+    use tiny_skia::Pixmap;
+
+    use render::game::{self, TurnLog};
+    use render::raster::Renderer;
+    use simulator::{Action, Simulator};
+    use solver::{Params, Solver};
+
+    enum Strategy {
+        Solver(Box<Solver>),
+        Wait,
+    }
+
+    impl Strategy {
+        fn parse(name: &str, params: Params) -> Result<Strategy, String> {
+            match name {
+                "solver" => {
+                    let mut solver = Solver::with_params(&simulator::IPoint::new(), 3, params);
+                    solver.enable_annotation();
+                    Ok(Strategy::Solver(Box::new(solver)))
+                }
+                // role 毎の判断を hint にして、探索で選び直す
+                "beam" => {
+                    let mut solver = Solver::with_params(&simulator::IPoint::new(), 3, params);
+                    solver.enable_annotation();
+                    solver.enable_beam_search();
+                    Ok(Strategy::Solver(Box::new(solver)))
+                }
+                "mcts" => {
+                    let mut solver = Solver::with_params(&simulator::IPoint::new(), 3, params);
+                    solver.enable_annotation();
+                    solver.enable_mcts();
+                    Ok(Strategy::Solver(Box::new(solver)))
+                }
+                "wait" => Ok(Strategy::Wait),
+                _ => Err(format!("unknown strategy `{}`", name)),
+            }
+        }
+
+        fn act(&mut self, sim: &Simulator, player_id: usize) -> (Vec<Action>, Vec<solver::Annotation>) {
+            match self {
+                Strategy::Solver(solver) => game::solve(solver, sim, player_id),
+                Strategy::Wait => (vec![Action::Wait { message: String::new() }; 3], vec![]),
+            }
+        }
+    }
+
+    struct Options {
+        replay: Option<String>,
+        seed: u64,
+        strategy: [String; 2],
+        params: Option<String>,
+        show_annotation: [bool; 2],
+        from: usize,
+        to: Option<usize>,
+        fps: f32,
+        out: String,
+    }
+
+    impl Options {
+        fn parse(args: Vec<String>) -> Result<Options, String> {
+            let mut ret = Options {
+                replay: None,
+                seed: 0,
+                strategy: ["solver".to_string(), "solver".to_string()],
+                params: None,
+                show_annotation: [false, false],
+                from: 0,
+                to: None,
+                fps: 5.0,
+                out: "out.gif".to_string(),
+            };
+
+            let mut args = args.into_iter();
+            while let Some(key) = args.next() {
+                let value = args.next().ok_or(format!("missing value for `{}`", key))?;
+                let invalid = || format!("invalid value for `{}`: `{}`", key, value);
+                match key.as_str() {
+                    "--replay" => ret.replay = Some(value),
+                    "--seed" => ret.seed = value.parse().map_err(|_| invalid())?,
+                    "--player1" => ret.strategy[0] = value,
+                    "--player2" => ret.strategy[1] = value,
+                    "--params" => ret.params = Some(value),
+                    "--annotation" => {
+                        ret.show_annotation = match value.as_str() {
+                            "none" => [false, false],
+                            "1" => [true, false],
+                            "2" => [false, true],
+                            "both" => [true, true],
+                            _ => return Err(invalid()),
+                        }
+                    }
+                    "--from" => ret.from = value.parse().map_err(|_| invalid())?,
+                    "--to" => ret.to = Some(value.parse().map_err(|_| invalid())?),
+                    "--fps" => {
+                        ret.fps = value.parse().map_err(|_| invalid())?;
+                        // GIF の delay は 1/100 秒単位の u16 なので、0 以下や収まらない値は受け付けない
+                        if !(1.0..=u16::MAX as f32).contains(&(100.0 / ret.fps).round()) {
+                            return Err(invalid());
+                        }
+                    }
+                    "--out" => ret.out = value,
+                    _ => return Err(format!("unknown option `{}`", key)),
+                }
+            }
+            Ok(ret)
+        }
+    }
+
+    pub fn run(args: Vec<String>) -> Result<(), String> {
+        let options = Options::parse(args)?;
+
+        let (seed, turn_log_list) = match &options.replay {
+            Some(path) => {
+                let text = std::fs::read_to_string(path).map_err(|e| format!("failed to open {}: {}", path, e))?;
+                let replay = game::parse_replay(&text)?;
+                let turn_log_list = replay
+                    .turn_list
+                    .into_iter()
+                    .map(|action_list| TurnLog {
+                        action_list,
+                        annotation_list: [vec![], vec![]],
+                    })
+                    .collect();
+                (replay.seed, turn_log_list)
+            }
+            None => {
+                let params = match &options.params {
+                    Some(path) => Params::load(path)?,
+                    None => Params::from_env()?,
+                };
+                let mut strategy_list = [
+                    Strategy::parse(&options.strategy[0], params)?,
+                    Strategy::parse(&options.strategy[1], params)?,
+                ];
+                (options.seed, play(options.seed, &mut strategy_list))
+            }
+        };
+
+        let to = options.to.unwrap_or(turn_log_list.len()).min(turn_log_list.len());
+        if to < options.from {
+            return Err(format!("no turn to export (--from {} --to {})", options.from, to));
+        }
+
+        let renderer = Renderer::new()?;
+        let mut frame_list = vec![];
+        let mut sim = Simulator::new(seed);
+        for turn in 0..=to {
+            if options.from <= turn {
+                let log = turn_log_list.get(turn);
+                let annotation_list = [0, 1].map(|player_id| match log {
+                    Some(log) if options.show_annotation[player_id] => &log.annotation_list[player_id][..],
+                    _ => &[],
+                });
+                frame_list.push(renderer.render(&sim, annotation_list));
+            }
+            if let Some(log) = turn_log_list.get(turn).filter(|_| turn < to) {
+                sim.next_state(log.action_list[0].clone(), log.action_list[1].clone());
+            }
+        }
+
+        if options.out.ends_with(".gif") {
+            write_gif(&options.out, &frame_list, options.fps)?;
+        } else {
+            std::fs::create_dir_all(&options.out).map_err(|e| format!("failed to create {}: {}", options.out, e))?;
+            for (i, frame) in frame_list.iter().enumerate() {
+                let path = format!("{}/frame_{:04}.png", options.out, options.from + i);
+                frame
+                    .save_png(&path)
+                    .map_err(|e| format!("failed to save {}: {}", path, e))?;
+            }
+        }
+        eprintln!("exported {} frames to {}", frame_list.len(), options.out);
+        Ok(())
+    }
+
+    /// seed から試合を最後までして、turn 毎の記録を返す
+    fn play(seed: u64, strategy_list: &mut [Strategy; 2]) -> Vec<TurnLog> {
+        let mut sim = Simulator::new(seed);
+        let mut ret = vec![];
+        while !sim.finish_game() {
+            let (player1_action, player1_annotation) = strategy_list[0].act(&sim, 0);
+            let (player2_action, player2_annotation) = strategy_list[1].act(&sim, 1);
+            sim.next_state(player1_action.clone(), player2_action.clone());
+            ret.push(TurnLog {
+                action_list: [player1_action, player2_action],
+                annotation_list: [player1_annotation, player2_annotation],
+            });
+        }
+        ret
+    }
+
+    fn write_gif(path: &str, frame_list: &[Pixmap], fps: f32) -> Result<(), String> {
+        let file = std::fs::File::create(path).map_err(|e| format!("failed to create {}: {}", path, e))?;
+        let (width, height) = (frame_list[0].width() as u16, frame_list[0].height() as u16);
+        let mut encoder = gif::Encoder::new(file, width, height, &[]).map_err(|e| e.to_string())?;
+        encoder.set_repeat(gif::Repeat::Infinite).map_err(|e| e.to_string())?;
+        for pixmap in frame_list.iter() {
+            // 背景が不透明なので、premultiplied のままで良い
+            let mut rgba = pixmap.data().to_vec();
+            let mut frame = gif::Frame::from_rgba_speed(width, height, &mut rgba, 10);
+            // 単位は 1/100 秒
+            frame.delay = (100.0 / fps).round() as u16;
+            encoder.write_frame(&frame).map_err(|e| e.to_string())?;
+        }
+        Ok(())
+    }
+}
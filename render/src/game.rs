@@ -0,0 +1,47 @@
+use simulator::replay::Replay;
+use simulator::{Action, Simulator};
+use solver::{Annotation, Solver};
+
+/// 1ターン分の両 player の action と、その時の solver の判断材料
+pub struct TurnLog {
+    pub action_list: [Vec<Action>; 2],
+    pub annotation_list: [Vec<Annotation>; 2],
+}
+
+/// replay をテキスト形式か JSON から読む
+pub fn parse_replay(text: &str) -> Result<Replay, String> {
+    if text.trim_start().starts_with('{') {
+        serde_json::from_str(text).map_err(|e| e.to_string())
+    } else {
+        Replay::parse(text)
+    }
+}
+
+/// path の拡張子が .json なら JSON、そうでなければテキスト形式で書く
+pub fn replay_to_string(replay: &Replay, path: &str) -> String {
+    if path.ends_with(".json") {
+        serde_json::to_string(replay).unwrap()
+    } else {
+        replay.to_text()
+    }
+}
+
+/// player_id 側の盤面を solver に見せて、action と判断材料を simulator 上の座標で返す
+pub fn solve(solver: &mut Solver, sim: &Simulator, player_id: usize) -> (Vec<Action>, Vec<Annotation>) {
+    let mut board = sim.to_board(player_id as i32);
+    if player_id == 0 {
+        board.dump();
+        return (solver.solve(&board), solver.annotation_list().to_vec());
+    }
+
+    // simulater 側で player2 は点対称に回して渡すので、action と annotation を戻す
+    board.point_symmetry();
+    board.dump();
+    let action_list = solver
+        .solve(&board)
+        .into_iter()
+        .map(|action| action.point_symmetry())
+        .collect();
+    let annotation_list = solver.annotation_list().iter().map(|a| a.point_symmetry()).collect();
+    (action_list, annotation_list)
+}
@@ -0,0 +1,12 @@
+#![forbid(unsafe_code)]
+#![warn(clippy::all, rust_2018_idioms)]
+
+//! 試合の盤面を描画要素にして、画像に描く
+//!
+//! eframe (winit, glutin) に依存しないので、window の無い headless な環境でもビルドして動かせる
+//! visualizer は同じ描画要素を egui の painter で描く
+
+pub mod game;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod raster;
+pub mod scene;
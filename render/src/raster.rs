@@ -0,0 +1,148 @@
+//! scene の描画要素を tiny-skia で画像に描く
+
+use ab_glyph::{Font, FontArc, PxScale, ScaleFont};
+use epaint::text::FontDefinitions;
+use epaint::{Color32, Pos2};
+use tiny_skia::{FillRule, Paint, PathBuilder, Pixmap, Transform};
+
+use simulator::Simulator;
+
+use crate::scene::{self, Shape};
+
+const BACKGROUND: Color32 = Color32::from_gray(235);
+
+/// scene の描画要素を、egui と同じ見た目になるように画像に描く
+pub struct Renderer {
+    font: FontArc,
+}
+
+impl Renderer {
+    pub fn new() -> Result<Renderer, String> {
+        // visualizer (egui) と同じ default font を使う
+        let font_data = FontDefinitions::default()
+            .font_data
+            .remove("Ubuntu-Light")
+            .ok_or("egui default font is not found")?;
+        let font = FontArc::try_from_vec(font_data.font.into_owned()).map_err(|e| e.to_string())?;
+        Ok(Renderer { font })
+    }
+
+    pub fn render(&self, sim: &Simulator, annotation_list: [&[solver::Annotation]; 2]) -> Pixmap {
+        let size = scene::map_size();
+        let mut pixmap = Pixmap::new(size.x as u32, size.y as u32).unwrap();
+        pixmap.fill(to_color(BACKGROUND));
+
+        for shape in scene::map_shape_list(sim, annotation_list).iter() {
+            self.draw_shape(&mut pixmap, shape);
+        }
+
+        // 左上に turn と両 player の状態
+        let [player1, player2] = &sim.components.player_list;
+        let header = format!(
+            "turn {}    player1 health: {} mana: {}    player2 health: {} mana: {}",
+            sim.turn, player1.health, player1.mana, player2.health, player2.mana
+        );
+        self.draw_text(
+            &mut pixmap,
+            Pos2 { x: 10.0, y: 24.0 },
+            &header,
+            16.0,
+            Color32::BLACK,
+            false,
+        );
+        pixmap
+    }
+
+    fn draw_shape(&self, pixmap: &mut Pixmap, shape: &Shape) {
+        // anti_alias は default で有効
+        let mut paint = Paint::default();
+
+        match shape {
+            Shape::Line { from, to, width, color } => {
+                let mut pb = PathBuilder::new();
+                pb.move_to(from.x, from.y);
+                pb.line_to(to.x, to.y);
+                if let Some(path) = pb.finish() {
+                    paint.set_color(to_color(*color));
+                    let stroke = tiny_skia::Stroke {
+                        width: *width,
+                        ..Default::default()
+                    };
+                    pixmap.stroke_path(&path, &paint, &stroke, Transform::identity(), None);
+                }
+            }
+            Shape::Circle {
+                center,
+                radius,
+                fill,
+                stroke,
+            } => {
+                if let Some(path) = PathBuilder::from_circle(center.x, center.y, *radius) {
+                    if let Some(fill) = fill {
+                        paint.set_color(to_color(*fill));
+                        pixmap.fill_path(&path, &paint, FillRule::Winding, Transform::identity(), None);
+                    }
+                    if let Some(stroke) = stroke {
+                        paint.set_color(to_color(stroke.color));
+                        let stroke = tiny_skia::Stroke {
+                            width: stroke.width,
+                            ..Default::default()
+                        };
+                        pixmap.stroke_path(&path, &paint, &stroke, Transform::identity(), None);
+                    }
+                }
+            }
+            Shape::Text { pos, text, color } => {
+                self.draw_text(pixmap, *pos, text, scene::FONT_SIZE, *color, true);
+            }
+        }
+    }
+
+    /// pos を下端 (centered なら下端中央、そうでなければ左下) として text を描く
+    fn draw_text(&self, pixmap: &mut Pixmap, pos: Pos2, text: &str, size: f32, color: Color32, centered: bool) {
+        let font = self.font.as_scaled(PxScale::from(size));
+
+        // 幅を求めるために、一度並べる
+        let mut glyph_list = vec![];
+        let mut x = 0.0;
+        let mut prev = None;
+        for c in text.chars() {
+            let id = font.glyph_id(c);
+            if let Some(prev) = prev {
+                x += font.kern(prev, id);
+            }
+            glyph_list.push((id, x));
+            x += font.h_advance(id);
+            prev = Some(id);
+        }
+
+        let left = if centered { pos.x - x / 2.0 } else { pos.x };
+        let baseline = pos.y + font.descent();
+        let (width, height) = (pixmap.width() as i32, pixmap.height() as i32);
+        let data = pixmap.data_mut();
+        for (id, x) in glyph_list {
+            let glyph = id.with_scale_and_position(font.scale, ab_glyph::point(left + x, baseline));
+            if let Some(outline) = self.font.outline_glyph(glyph) {
+                let bounds = outline.px_bounds();
+                outline.draw(|gx, gy, coverage| {
+                    let px = bounds.min.x as i32 + gx as i32;
+                    let py = bounds.min.y as i32 + gy as i32;
+                    if px < 0 || py < 0 || width <= px || height <= py {
+                        return;
+                    }
+                    // 背景は不透明なので、色だけ混ぜる
+                    let i = 4 * (py * width + px) as usize;
+                    let alpha = coverage.clamp(0.0, 1.0) * color.a() as f32 / 255.0;
+                    for (c, src) in data[i..i + 3].iter_mut().zip([color.r(), color.g(), color.b()]) {
+                        *c = (src as f32 * alpha + *c as f32 * (1.0 - alpha)).round() as u8;
+                    }
+                });
+            }
+        }
+    }
+}
+
+fn to_color(color: Color32) -> tiny_skia::Color {
+    let [r, g, b, a] = color.to_srgba_unmultiplied();
+    tiny_skia::Color::from_rgba8(r, g, b, a)
+}
@@ -0,0 +1,188 @@
+use epaint::{Color32, Pos2, Stroke, Vec2};
+
+use simulator::{IPoint, Simulator, MAP_LIMIT, MAX_X, MAX_Y};
+use solver::Annotation;
+
+/// map の描画要素 (座標は画面上の pixel)
+///
+/// visualizer の egui の painter でも、export の raster でも同じものを描くために、一度この形にする
+#[derive(Debug, Clone)]
+pub enum Shape {
+    Line {
+        from: Pos2,
+        to: Pos2,
+        width: f32,
+        color: Color32,
+    },
+    Circle {
+        center: Pos2,
+        radius: f32,
+        fill: Option<Color32>,
+        stroke: Option<Stroke>,
+    },
+    /// pos を下端中央として描く
+    Text { pos: Pos2, text: String, color: Color32 },
+}
+
+pub const FONT_SIZE: f32 = 11.0;
+
+const SCALE: f32 = MAX_X as f32 / 1080.0;
+const OFFSET: f32 = MAP_LIMIT as f32 / SCALE + 50.0;
+
+// player1, player2 の annotation の色
+const ANNOTATION_COLOR: [Color32; 2] = [Color32::from_rgb(255, 140, 0), Color32::from_rgb(0, 160, 160)];
+
+pub fn to_pos(p: &IPoint) -> Pos2 {
+    Pos2 {
+        x: p.x as f32 / SCALE + OFFSET,
+        y: p.y as f32 / SCALE + OFFSET,
+    }
+}
+
+/// map 全体を描くのに必要な大きさ (真の外枠の外側にも、左上と同じだけ余白を取る)
+pub fn map_size() -> Vec2 {
+    to_pos(&IPoint {
+        x: MAX_X + MAP_LIMIT,
+        y: MAX_Y + MAP_LIMIT,
+    })
+    .to_vec2()
+        + Vec2::splat(50.0)
+}
+
+fn line(p1: &IPoint, p2: &IPoint, color: Color32) -> Shape {
+    Shape::Line {
+        from: to_pos(p1),
+        to: to_pos(p2),
+        width: 2.0,
+        color,
+    }
+}
+
+fn entity(p: &IPoint, color: Color32) -> Shape {
+    Shape::Circle {
+        center: to_pos(p),
+        radius: 5.0,
+        fill: Some(Color32::WHITE),
+        stroke: Some(Stroke { width: 5.0, color }),
+    }
+}
+
+fn text(p: &IPoint, text: String, color: Color32) -> Shape {
+    Shape::Text {
+        pos: to_pos(p) + Vec2 { x: 0.0, y: -12.0 },
+        text,
+        color,
+    }
+}
+
+/// sim の盤面と、player 毎の annotation を描画要素にする
+pub fn map_shape_list(sim: &Simulator, annotation_list: [&[Annotation]; 2]) -> Vec<Shape> {
+    let mut ret = vec![];
+
+    // 外枠
+    let points = [
+        IPoint { x: 0, y: 0 },
+        IPoint { x: MAX_X, y: 0 },
+        IPoint { x: MAX_X, y: MAX_Y },
+        IPoint { x: 0, y: MAX_Y },
+    ];
+    for i in 0..4 {
+        ret.push(line(&points[i], &points[(i + 1) % 4], Color32::BLACK));
+    }
+
+    // 真の外枠
+    let points = [
+        IPoint {
+            x: -MAP_LIMIT + 1,
+            y: -MAP_LIMIT + 1,
+        },
+        IPoint {
+            x: MAX_X + MAP_LIMIT - 1,
+            y: -MAP_LIMIT + 1,
+        },
+        IPoint {
+            x: MAX_X + MAP_LIMIT - 1,
+            y: MAX_Y + MAP_LIMIT - 1,
+        },
+        IPoint {
+            x: -MAP_LIMIT + 1,
+            y: MAX_Y + MAP_LIMIT - 1,
+        },
+    ];
+    for i in 0..4 {
+        ret.push(line(&points[i], &points[(i + 1) % 4], Color32::GRAY));
+    }
+
+    let spawn_list = [
+        IPoint {
+            x: MAX_X / 2,
+            y: -MAP_LIMIT + 1,
+        },
+        IPoint {
+            x: MAX_X / 2 + 4000,
+            y: -MAP_LIMIT + 1,
+        },
+        IPoint {
+            x: MAX_X / 2,
+            y: MAX_Y + MAP_LIMIT - 1,
+        },
+        IPoint {
+            x: MAX_X / 2 - 4000,
+            y: MAX_Y + MAP_LIMIT - 1,
+        },
+    ];
+    for point in spawn_list.iter() {
+        ret.push(entity(point, Color32::GRAY));
+    }
+
+    // hero
+    for (player, is_self) in sim.components.player_list.iter().zip([true, false]) {
+        let color = if is_self { Color32::RED } else { Color32::BLUE };
+        for hero in player.hero_list.iter() {
+            ret.push(entity(&hero.component.position, color));
+        }
+    }
+
+    // monster
+    for monster in sim.components.monster_list.iter() {
+        ret.push(entity(&monster.component.position, Color32::BLACK));
+    }
+
+    // solver の判断材料
+    for (player_id, annotation_list) in annotation_list.iter().enumerate() {
+        let color = ANNOTATION_COLOR[player_id];
+        for annotation in annotation_list.iter() {
+            match annotation {
+                Annotation::Circle { center, radius, .. } => {
+                    ret.push(Shape::Circle {
+                        center: to_pos(center),
+                        radius: *radius as f32 / SCALE,
+                        fill: None,
+                        stroke: Some(Stroke { width: 1.0, color }),
+                    });
+                }
+                Annotation::Line { from, to, label } => {
+                    ret.push(line(from, to, color));
+                    ret.push(text(to, label.clone(), color));
+                }
+                Annotation::Candidate { point, score, label } => {
+                    ret.push(Shape::Circle {
+                        center: to_pos(point),
+                        radius: 3.0,
+                        fill: Some(color),
+                        stroke: None,
+                    });
+                    ret.push(text(point, format!("{}:{}", label, score), color));
+                }
+                Annotation::Role { hero_id, role } => {
+                    let hero = &sim.components.player_list[player_id].hero_list[*hero_id];
+                    let pos = hero.component.position + IPoint { x: 0, y: 600 };
+                    ret.push(text(&pos, role.clone(), color));
+                }
+                Annotation::Text { .. } => {}
+            }
+        }
+    }
+
+    ret
+}
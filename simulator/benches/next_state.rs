@@ -0,0 +1,66 @@
+//! GameState::next_state の throughput を測る
+//!
+//! ```text
+//! cargo bench -p simulator --bench next_state
+//! ```
+//!
+//! seed 毎に最初の盤面から、決めておいた手で試合の最後まで何度も進めて、1秒あたりに進めた盤面の数を出す
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use simulator::state::{Command, GameState, HERO_SIZE};
+use simulator::{IPoint, Simulator, XorShift, MAX_X, MAX_Y};
+
+const SEED_COUNT: u64 = 8;
+const ROLLOUT_COUNT: usize = 2000;
+const MAX_TURN: usize = 220;
+
+/// 手を決める時間を測らないように、試合の最後までの手を先に作っておく
+fn command_list(rand: &mut XorShift) -> Vec<[[Command; HERO_SIZE]; 2]> {
+    let mut point = || IPoint {
+        x: (rand.next() % MAX_X as u64) as i32,
+        y: (rand.next() % MAX_Y as u64) as i32,
+    };
+    (0..MAX_TURN)
+        .map(|_| {
+            [
+                [(); HERO_SIZE].map(|_| Command::Move(point())),
+                [(); HERO_SIZE].map(|_| Command::Move(point())),
+            ]
+        })
+        .collect()
+}
+
+fn main() {
+    let mut state_count = 0;
+    let mut elapsed = Duration::ZERO;
+    for seed in 0..SEED_COUNT {
+        let sim = Simulator::new(seed);
+        let table = sim.random().clone();
+        let root = GameState::from_simulator(&sim, &table);
+        let command_list = command_list(&mut XorShift::new(seed + 100));
+
+        let start = Instant::now();
+        for _ in 0..ROLLOUT_COUNT {
+            let mut state = root;
+            for command in command_list.iter() {
+                if state.finish_game() {
+                    break;
+                }
+                state.next_state(black_box(command));
+                state_count += 1;
+            }
+            black_box(&state);
+        }
+        elapsed += start.elapsed();
+    }
+
+    let second = elapsed.as_secs_f64();
+    println!(
+        "next_state: {} states in {:.3} s ({:.0} states/s)",
+        state_count,
+        second,
+        state_count as f64 / second
+    );
+}
@@ -37,8 +37,30 @@ impl XorShift {
     }
 }
 
-#[derive(Debug)]
+/// Simulator と GameState で、同じ乱数の使い方をするための共通部分
+pub trait Random {
+    fn next_int(&mut self) -> u32;
+
+    fn next_float(&mut self) -> f64;
+
+    fn next_boolean(&mut self) -> bool {
+        self.next_float() <= 0.5
+    }
+
+    fn next_int_range(&mut self, left: u32, right: u32) -> u32 {
+        ((((right - left) as u64) * self.next_int() as u64) >> 32) as u32 + left
+    }
+
+    fn next_float_range(&mut self, left: f64, right: f64) -> f64 {
+        self.next_float() * (right - left) + left
+    }
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(from = "CachedRandomState", into = "CachedRandomState"))]
 pub struct CachedRandom {
+    seed: u64,
     int_table: Vec<u32>,
     uniform_table: Vec<f64>,
     log_table: Vec<f64>,
@@ -48,6 +70,7 @@ pub struct CachedRandom {
 impl CachedRandom {
     pub fn new(size: usize, seed: u64) -> CachedRandom {
         let mut ret = CachedRandom {
+            seed,
             int_table: vec![],
             uniform_table: vec![],
             log_table: vec![],
@@ -71,6 +94,10 @@ impl CachedRandom {
         ret
     }
 
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
     pub fn next_boolean(&mut self) -> bool {
         self.next_float() <= 0.5
     }
@@ -89,7 +116,7 @@ impl CachedRandom {
     }
 
     pub fn next_int_range(&mut self, left: u32, right: u32) -> u32 {
-        (((right - left) as u64) * self.next_int() as u64 >> 32) as u32 + left
+        ((((right - left) as u64) * self.next_int() as u64) >> 32) as u32 + left
     }
 
     pub fn next_float(&mut self) -> f64 {
@@ -120,6 +147,48 @@ impl CachedRandom {
     }
 }
 
+impl Random for CachedRandom {
+    fn next_int(&mut self) -> u32 {
+        CachedRandom::next_int(self)
+    }
+
+    fn next_float(&mut self) -> f64 {
+        CachedRandom::next_float(self)
+    }
+}
+
+/// CachedRandom の table は seed と size から作り直せるので、保存するのはそれらと乱数の位置だけ
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct CachedRandomState {
+    seed: u64,
+    size: usize,
+    index: usize,
+}
+
+#[cfg(feature = "serde")]
+impl From<CachedRandomState> for CachedRandom {
+    fn from(state: CachedRandomState) -> Self {
+        let mut ret = CachedRandom::new(state.size, state.seed);
+        ret.index = state.index;
+        ret
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<CachedRandom> for CachedRandomState {
+    fn from(random: CachedRandom) -> Self {
+        CachedRandomState {
+            seed: random.seed,
+            size: random.len(),
+            index: random.index,
+        }
+    }
+}
+
+pub mod replay;
+pub mod state;
+
 // inout info
 
 pub mod inout {
@@ -128,7 +197,8 @@ pub mod inout {
     use crate::CENTER;
     pub use crate::{Action, MonsterThreatState};
 
-    #[derive(Debug)]
+    #[derive(Debug, Clone)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     pub struct Board {
         pub player: Player,
         pub opponent: Player,
@@ -182,7 +252,8 @@ pub mod inout {
         }
     }
 
-    #[derive(Debug)]
+    #[derive(Debug, Clone)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     pub struct Player {
         pub health: i32,
         pub mana: i32,
@@ -201,7 +272,8 @@ pub mod inout {
         }
     }
 
-    #[derive(Debug)]
+    #[derive(Debug, Clone)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     pub struct Hero {
         pub id: i32,
         pub pos: Point,
@@ -209,7 +281,8 @@ pub mod inout {
         pub is_controlled: bool,
     }
 
-    #[derive(Debug)]
+    #[derive(Debug, Clone)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     pub struct Monster {
         pub id: i32,
         pub pos: Point,
@@ -349,6 +422,7 @@ impl Number for f64 {
 }
 
 #[derive(Copy, Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Point<T>
 where
     T: Number,
@@ -358,7 +432,7 @@ where
 }
 
 impl Point<f64> {
-    fn to<T>(&self) -> Point<T>
+    pub fn to<T>(&self) -> Point<T>
     where
         T: Number,
     {
@@ -567,6 +641,199 @@ mod test {
             assert!(false);
         }
     }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_round_trip() {
+        use crate::{Action, IPoint, Simulator};
+
+        let move_to = |x, y| {
+            vec![
+                Action::Move {
+                    point: IPoint { x, y },
+                    message: String::new(),
+                };
+                3
+            ]
+        };
+
+        let mut sim = Simulator::new(3);
+        for _ in 0..7 {
+            sim.next_state(move_to(5000, 5000), move_to(12000, 4000));
+        }
+
+        // 乱数の位置も含めて復元できていれば、その後の spawn も一致する
+        let json = serde_json::to_string(&sim).unwrap();
+        let mut restored: Simulator = serde_json::from_str(&json).unwrap();
+        for _ in 0..20 {
+            sim.next_state(move_to(3000, 6000), move_to(15000, 2000));
+            restored.next_state(move_to(3000, 6000), move_to(15000, 2000));
+        }
+        assert_eq!(
+            serde_json::to_string(&sim).unwrap(),
+            serde_json::to_string(&restored).unwrap()
+        );
+    }
+
+    use crate::{Action, FPoint, IPoint, Simulator, MAP_LIMIT, MAX_MONSTER_VELOCITY, MAX_X, MAX_Y};
+
+    fn wait() -> Vec<Action> {
+        vec![Action::Wait { message: String::new() }; 3]
+    }
+
+    // monster の出現しないターンで、hero を全員盤面の中央に置いた simulator
+    fn quiet_simulator() -> Simulator {
+        let mut sim = Simulator::new(0);
+        sim.turn = 1;
+        for player in sim.components.player_list.iter_mut() {
+            player.mana = 100;
+            for hero in player.hero_list.iter_mut() {
+                hero.component.position = Point {
+                    x: MAX_X / 2,
+                    y: MAX_Y / 2,
+                };
+            }
+        }
+        sim
+    }
+
+    fn monster_at(sim: &mut Simulator, x: i32, y: i32, v: IPoint) {
+        sim.create_monster(
+            FPoint {
+                x: x as f64,
+                y: y as f64,
+            },
+            v,
+        );
+    }
+
+    #[test]
+    fn test_attack_base() {
+        // base の 300 以内に入った monster は、base に 1 ダメージを与えて消える (点対称な側も)
+        let mut sim = quiet_simulator();
+        monster_at(&mut sim, 400, 400, Point { x: -283, y: -283 });
+        sim.next_state(wait(), wait());
+        assert_eq!(sim.components.player_list[0].health, 2);
+        assert_eq!(sim.components.player_list[1].health, 2);
+        assert!(sim.components.monster_list.is_empty());
+    }
+
+    #[test]
+    fn test_wild_mana() {
+        // 自陣の 5000 の外で殴った分だけが wild mana になる
+        let mut sim = quiet_simulator();
+        monster_at(&mut sim, 2000, 2000, Point { x: 0, y: 0 });
+        monster_at(&mut sim, 10000, 1000, Point { x: 0, y: 0 });
+        sim.components.player_list[0].hero_list[0].component.position = Point { x: 2000, y: 2000 };
+        sim.components.player_list[0].hero_list[1].component.position = Point { x: 10000, y: 1000 };
+        sim.next_state(wait(), wait());
+        let player = &sim.components.player_list[0];
+        assert_eq!(player.mana, 104);
+        assert_eq!(player.wild_mana, 2);
+    }
+
+    #[test]
+    fn test_move_without_overshoot() {
+        // 800 以内の目的地は通り過ぎずに止まる
+        let mut sim = quiet_simulator();
+        sim.components.player_list[0].hero_list[0].component.position = Point { x: 1000, y: 1000 };
+        let mut action_list = wait();
+        action_list[0] = Action::Move {
+            point: Point { x: 1300, y: 1400 },
+            message: String::new(),
+        };
+        sim.next_state(action_list, wait());
+        assert_eq!(
+            sim.components.player_list[0].hero_list[0].component.position,
+            Point { x: 1300, y: 1400 }
+        );
+    }
+
+    #[test]
+    fn test_control_keeps_direction() {
+        // control された monster はそのターンに指定した方に動き、以降もその向きに進む
+        let mut sim = quiet_simulator();
+        monster_at(&mut sim, 8000, 3000, Point { x: 400, y: 0 });
+        sim.components.player_list[0].hero_list[0].component.position = Point { x: 8000, y: 4000 };
+        let id = sim.components.monster_list[0].component.id;
+        let mut action_list = wait();
+        action_list[0] = Action::Control {
+            entity_id: id,
+            point: Point { x: 8000, y: 0 },
+            message: String::new(),
+        };
+        sim.next_state(action_list, wait());
+        let monster = &sim.components.monster_list[0].component;
+        assert_eq!(monster.position, Point { x: 8000, y: 2600 });
+        assert_eq!(
+            monster.velocity,
+            Point {
+                x: 0,
+                y: -MAX_MONSTER_VELOCITY
+            }
+        );
+
+        sim.next_state(wait(), wait());
+        assert_eq!(
+            sim.components.monster_list[0].component.position,
+            Point { x: 8000, y: 2200 }
+        );
+    }
+
+    #[test]
+    fn test_base_attraction() {
+        // base の 5000 以内に入った monster は、base に向かう
+        let mut sim = quiet_simulator();
+        monster_at(&mut sim, 3000, 3000, Point { x: 400, y: 0 });
+        sim.next_state(wait(), wait());
+        let monster = &sim.components.monster_list[0].component;
+        assert_eq!(monster.position, Point { x: 3400, y: 3000 });
+        let expected = (Point { x: -3400, y: -3000 }.to_f64().normalize() * MAX_MONSTER_VELOCITY as f64).to::<i32>();
+        assert_eq!(monster.velocity, expected);
+    }
+
+    #[test]
+    fn test_wind_order() {
+        // wind で base の視界の外に押し出された monster は、id の小さい順に乱数で向きが決まる
+        let mut sim = quiet_simulator();
+        monster_at(&mut sim, 3000, 3500, Point { x: 0, y: 0 });
+        monster_at(&mut sim, 3300, 3400, Point { x: 0, y: 0 });
+        sim.components.player_list[0].hero_list[0].component.position = Point { x: 3100, y: 3300 };
+        let mut random = sim.system.random.clone();
+        let expected = [
+            Simulator::random_velocity(&mut random, MAX_MONSTER_VELOCITY),
+            Simulator::random_velocity(&mut random, MAX_MONSTER_VELOCITY),
+        ];
+
+        let mut action_list = wait();
+        action_list[0] = Action::Wind {
+            point: Point { x: 3100, y: 4300 },
+            message: String::new(),
+        };
+        sim.next_state(action_list, wait());
+        let mut pushed = sim
+            .components
+            .monster_list
+            .iter()
+            .filter(|m| m.component.pushed)
+            .map(|m| (m.component.id, m.component.velocity))
+            .collect::<Vec<_>>();
+        pushed.sort_by_key(|(id, _)| *id);
+        assert_eq!(pushed.iter().map(|(_, v)| *v).collect::<Vec<_>>(), expected);
+    }
+
+    #[test]
+    fn test_is_in_gameboard() {
+        // y が盤面の下に出ていれば、x が盤面の中でも外
+        assert!(Simulator::is_in_gameboard(&Point {
+            x: 1000,
+            y: MAX_Y + MAP_LIMIT
+        }));
+        assert!(!Simulator::is_in_gameboard(&Point {
+            x: 1000,
+            y: MAX_Y + MAP_LIMIT + 1
+        }));
+    }
 }
 
 impl<T: Number> Line<T> {
@@ -579,6 +846,7 @@ impl<T: Number> Line<T> {
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Player {
     pub health: i32,
     pub mana: i32,
@@ -615,6 +883,7 @@ impl Player {
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Hero {
     pub component: Component,
     pub action: Action,
@@ -622,6 +891,7 @@ pub struct Hero {
 }
 
 #[derive(Clone, Copy, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum MonsterThreatState {
     NotThreat,                 // nearBase == 0 && threatFor == 0
     PlayerThreatInTheFuture,   // nearBase == 0 && threatFor == 1
@@ -675,6 +945,7 @@ impl MonsterThreatState {
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Monster {
     pub component: Component,
     pub health: i32,
@@ -692,6 +963,7 @@ impl Monster {
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 enum ComponentType {
     Monster,
     PlayerHero,
@@ -705,6 +977,7 @@ impl ComponentType {
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Component {
     pub id: i32,
     pub position: IPoint,
@@ -744,6 +1017,7 @@ impl Component {
 }
 
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Action {
     Wait {
         message: String,
@@ -767,7 +1041,35 @@ pub enum Action {
     },
 }
 
-#[derive(Debug)]
+impl Action {
+    /// 点対称に回した盤面上での Action に変換する
+    pub fn point_symmetry(self) -> Action {
+        match self {
+            Action::Wait { message } => Action::Wait { message },
+            Action::Move { point, message } => Action::Move {
+                point: point.point_symmetry(&CENTER),
+                message,
+            },
+            Action::Wind { point, message } => Action::Wind {
+                point: point.point_symmetry(&CENTER),
+                message,
+            },
+            Action::Shield { entity_id, message } => Action::Shield { entity_id, message },
+            Action::Control {
+                entity_id,
+                point,
+                message,
+            } => Action::Control {
+                entity_id,
+                point: point.point_symmetry(&CENTER),
+                message,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 struct SpawnLocation {
     // 発生場所
     pos: FPoint,
@@ -786,9 +1088,15 @@ impl SpawnLocation {
             },
         }
     }
+
+    // 点対称な側は create_monster で作る
+    fn list() -> [SpawnLocation; 2] {
+        SPAWN_POINT_LIST.map(|p| SpawnLocation::new(p.to_f64()))
+    }
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct System {
     unique_id: i32,
     random: CachedRandom,
@@ -803,6 +1111,7 @@ impl System {
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Components {
     pub player_list: [Player; 2],
     pub monster_list: Vec<Monster>,
@@ -840,13 +1149,13 @@ impl Components {
         for player in self.player_list.iter_mut() {
             for hero in player.hero_list.iter_mut() {
                 if hero.component.id == id {
-                    Some(&mut hero.component);
+                    return Some(&mut hero.component);
                 }
             }
         }
         for monster in self.monster_list.iter_mut() {
             if monster.component.id == id {
-                Some(&mut monster.component);
+                return Some(&mut monster.component);
             }
         }
         None
@@ -878,6 +1187,7 @@ impl Components {
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Simulator {
     pub components: Components,
     pub turn: usize,
@@ -906,6 +1216,21 @@ pub const CONTROL_EFFECTIVE_RADIUS: i32 = 2200;
 pub const WIND_DISTANCE: i32 = 2200;
 pub const VISIBLE_RADIUS_FROM_BASE: i32 = 6000;
 pub const VISIBLE_RADIUS_FROM_HERO: i32 = 2200;
+// この範囲に入った monster は base に向かって進む
+pub const BASE_ATTRACTION_RADIUS: i32 = 5000;
+// ターン終了時にこの範囲にいる monster は base にダメージを与えて消える
+pub const BASE_ATTACK_RADIUS: i32 = 300;
+// monster が湧く地点 (点対称な側は CENTER で反転した所にも湧く)
+pub const SPAWN_POINT_LIST: [IPoint; 2] = [
+    IPoint {
+        x: MAX_X / 2,
+        y: -MAP_LIMIT + 1,
+    },
+    IPoint {
+        x: MAX_X / 2 + 4000,
+        y: -MAP_LIMIT + 1,
+    },
+];
 pub const MOB_SPAWN_MAX_DIRECTION_DELTA: f64 = 5.0 * std::f64::consts::PI / 12.0;
 
 #[derive(Clone, Copy)]
@@ -930,16 +1255,7 @@ impl Simulator {
                 monster_list: vec![],
             },
             turn: 0,
-            spawn_location: vec![
-                SpawnLocation::new(Point {
-                    y: (-MAP_LIMIT + 1) as f64,
-                    x: (MAX_X / 2) as f64,
-                }),
-                SpawnLocation::new(Point {
-                    y: (-MAP_LIMIT + 1) as f64,
-                    x: (MAX_X / 2 + 4000) as f64,
-                }),
-            ],
+            spawn_location: SpawnLocation::list().to_vec(),
             activated_hero: vec![],
         };
 
@@ -971,6 +1287,11 @@ impl Simulator {
         }
     }
 
+    /// GameState で乱数の table を共有するために使う
+    pub fn random(&self) -> &CachedRandom {
+        &self.system.random
+    }
+
     pub fn finish_game(&self) -> bool {
         self.components.player().health == 0 || self.components.opponent().health == 0 || self.turn == 220
     }
@@ -1096,7 +1417,8 @@ impl Simulator {
 
     /// WIND は実行したら即座に場所が反映される
     fn do_wind(&mut self) {
-        let mut diff = std::collections::HashMap::<i32, Vec<IPoint>>::new();
+        // 乱数を使う順番が変わらないように、id 順に適用する
+        let mut diff = std::collections::BTreeMap::<i32, Vec<IPoint>>::new();
 
         for player_id in 0..2 {
             for hero in self.components.player_list[player_id].hero_list.iter() {
@@ -1139,33 +1461,47 @@ impl Simulator {
             component.pushed = true;
             let sum_diff = v.iter().fold(Point::new(), |p1, p2| p1 + *p2);
 
-            let np = component.position + sum_diff;
+            let p = component.position;
+            let np = p + sum_diff;
 
             // base 範囲内から外へ出る動きをする場合 || hero は、外に出ずに停止する
-            if component.component_type.is_hero() || Simulator::go_outside_around_base(&component.position, &np) {
+            if component.component_type.is_hero() || Simulator::go_outside_around_base(&p, &np) {
                 component.position = Simulator::snap_to_game_zone(np);
             } else {
                 component.position = np;
                 // base 近辺から外に出た monster
-                if !component.component_type.is_hero() && Simulator::go_out_from_base(&component.position, &np) {
-                    // ランダムに回転して方向が決まる
-                    let angle = self.system.random.next_float() * std::f64::consts::PI * 2.0;
-                    let vy = (angle.sin() * (component.max_velocity as f64)).round() as i32;
-                    let vx = (angle.cos() * (component.max_velocity as f64)).round() as i32;
-                    component.velocity = Point { y: vy, x: vx };
+                if Simulator::go_out_from_base(&p, &np) {
+                    component.velocity = Simulator::random_velocity(&mut self.system.random, component.max_velocity);
                 }
             }
         }
     }
 
+    /// target に向かって speed で進む時の移動量
     fn calculate_real_target(pos: &IPoint, target: &IPoint, speed: i32) -> IPoint {
         if pos.in_range(&target, speed) {
-            *target
+            *target - *pos
         } else {
             ((*target - *pos).to_f64().normalize() * (speed as f64)).to::<i32>()
         }
     }
 
+    // ランダムに回転して方向が決まる
+    fn random_velocity(random: &mut impl Random, max_velocity: i32) -> IPoint {
+        let angle = random.next_float() * std::f64::consts::PI * 2.0;
+        let vy = (angle.sin() * (max_velocity as f64)).round() as i32;
+        let vx = (angle.cos() * (max_velocity as f64)).round() as i32;
+        Point { y: vy, x: vx }
+    }
+
+    /// base の近くにいる monster が、その base に向かう速度
+    fn velocity_to_base(p: &IPoint) -> Option<IPoint> {
+        [Point { x: 0, y: 0 }, Point { x: MAX_X, y: MAX_Y }]
+            .iter()
+            .find(|base| base.in_range(p, BASE_ATTRACTION_RADIUS))
+            .map(|base| ((*base - *p).to_f64().normalize() * MAX_MONSTER_VELOCITY as f64).to::<i32>())
+    }
+
     fn go_out_from_base(p: &IPoint, np: &IPoint) -> bool {
         [Point { x: 0, y: 0 }, Point { x: MAX_X, y: MAX_Y }]
             .iter()
@@ -1225,8 +1561,9 @@ impl Simulator {
         for player in self.components.player_list.iter_mut() {
             for hero in player.hero_list.iter_mut() {
                 if let Action::Move { point, message: _ } = hero.action {
+                    // 目的地を通り過ぎない
                     hero.component.velocity =
-                        ((point - hero.component.position).to_f64().normalize() * (MAX_HERO_VELOCITY as f64)).to();
+                        Self::calculate_real_target(&hero.component.position, &point, MAX_HERO_VELOCITY);
                     // 外に出ないための対策
                     hero.component.position = Self::snap_to_game_zone(hero.component.next_pos());
                 }
@@ -1250,9 +1587,10 @@ impl Simulator {
                     {
                         monster.health -= MANA_GAIN_TO_ATTACK;
                         mana_gain[player_id].all_mana += MANA_GAIN_TO_ATTACK;
-                        if player
+                        // 自陣の外で得たマナ
+                        if !player
                             .base
-                            .in_range(&monster.component.position, VISIBLE_RADIUS_FROM_BASE)
+                            .in_range(&monster.component.position, BASE_ATTRACTION_RADIUS)
                         {
                             mana_gain[player_id].wild_mana += MANA_GAIN_TO_ATTACK;
                         }
@@ -1294,54 +1632,87 @@ impl Simulator {
         self.components.monster_list.push(monster);
     }
 
-    fn adjust_monster(&mut self) {
-        // remove dead monster
-        self.components.monster_list.retain(|m| {
-            m.health > 0
-                && -MAP_LIMIT + 1 <= m.component.position.x
-                && m.component.position.x <= MAX_X + MAP_LIMIT - 1
-                && -MAP_LIMIT + 1 <= m.component.position.y
-                && m.component.position.y <= MAX_Y + MAP_LIMIT - 1
-        });
+    /// base に辿り着いた monster は、base にダメージを与えて消える
+    fn attack_base(&mut self) {
+        for monster in self.components.monster_list.iter_mut().filter(|m| m.health > 0) {
+            for player in self.components.player_list.iter_mut() {
+                if player.base.in_range(&monster.component.position, BASE_ATTACK_RADIUS) {
+                    player.health = Number::max(0, player.health - 1);
+                    monster.health = 0;
+                    break;
+                }
+            }
+        }
+    }
 
-        let sudden_death = self.turn >= 200;
+    // 倒されておらず、盤外に出ていない monster
+    fn monster_remains(health: i32, p: &IPoint) -> bool {
+        health > 0
+            && -MAP_LIMIT + 1 <= p.x
+            && p.x <= MAX_X + MAP_LIMIT - 1
+            && -MAP_LIMIT + 1 <= p.y
+            && p.y <= MAX_Y + MAP_LIMIT - 1
+    }
+
+    /// turn に出現する monster の位置と速度を、spawn location 毎に f に渡す (点対称な側は含まない)
+    fn spawn(
+        turn: usize,
+        spawn_location: &[SpawnLocation],
+        random: &mut impl Random,
+        mut f: impl FnMut(FPoint, IPoint),
+    ) {
+        let sudden_death = turn >= 200;
+        if !sudden_death && !turn.is_multiple_of(5) {
+            return;
+        }
 
-        // appear new monster
-        if sudden_death || self.turn % 5 == 0 {
-            for loc_id in 0..self.spawn_location.len() {
-                if sudden_death {
-                    let mut tx = self.system.random.next_int_range(0, VISIBLE_RADIUS_FROM_BASE as u32) as i32;
-                    let mut ty = self.system.random.next_int_range(0, VISIBLE_RADIUS_FROM_BASE as u32) as i32;
-                    if self.system.random.next_boolean() {
-                        tx = MAX_X - tx;
-                        ty = MAX_Y - ty;
-                    }
-                    let target = FPoint {
-                        x: tx as f64,
-                        y: ty as f64,
-                    };
-
-                    let position = self.spawn_location[loc_id].pos;
-                    let velocity = ((target - position).normalize() * MAX_MONSTER_VELOCITY as f64).to::<i32>();
-                    self.create_monster(position, velocity);
-                } else {
-                    let direction_delta = self
-                        .system
-                        .random
-                        .next_float_range(-MOB_SPAWN_MAX_DIRECTION_DELTA, MOB_SPAWN_MAX_DIRECTION_DELTA);
-
-                    let position = self.spawn_location[loc_id].pos;
-                    let velocity = (self.spawn_location[loc_id].dir.rotate(direction_delta)
-                        * MAX_MONSTER_VELOCITY as f64)
-                        .to::<i32>();
-                    self.create_monster(position, velocity);
+        for location in spawn_location.iter() {
+            if sudden_death {
+                let mut tx = random.next_int_range(0, VISIBLE_RADIUS_FROM_BASE as u32) as i32;
+                let mut ty = random.next_int_range(0, VISIBLE_RADIUS_FROM_BASE as u32) as i32;
+                if random.next_boolean() {
+                    tx = MAX_X - tx;
+                    ty = MAX_Y - ty;
                 }
+                let target = FPoint {
+                    x: tx as f64,
+                    y: ty as f64,
+                };
+
+                let velocity = ((target - location.pos).normalize() * MAX_MONSTER_VELOCITY as f64).to::<i32>();
+                f(location.pos, velocity);
+            } else {
+                let direction_delta =
+                    random.next_float_range(-MOB_SPAWN_MAX_DIRECTION_DELTA, MOB_SPAWN_MAX_DIRECTION_DELTA);
+
+                let velocity = (location.dir.rotate(direction_delta) * MAX_MONSTER_VELOCITY as f64).to::<i32>();
+                f(location.pos, velocity);
             }
         }
     }
 
-    fn is_in_gameboard(p: &Point<i32>) -> bool {
-        -MAP_LIMIT <= p.x && p.x <= MAX_X + MAP_LIMIT && -MAP_LIMIT <= p.y && p.x <= MAX_Y + MAP_LIMIT
+    fn adjust_monster(&mut self) {
+        // remove dead monster
+        self.components
+            .monster_list
+            .retain(|m| Simulator::monster_remains(m.health, &m.component.position));
+
+        // appear new monster
+        let mut spawn_list = vec![];
+        Simulator::spawn(
+            self.turn,
+            &self.spawn_location,
+            &mut self.system.random,
+            |position, velocity| spawn_list.push((position, velocity)),
+        );
+        for (position, velocity) in spawn_list {
+            self.create_monster(position, velocity);
+        }
+    }
+
+    /// p が盤面 (外側の余白 MAP_LIMIT を含む) の中にあるか
+    pub fn is_in_gameboard(p: &Point<i32>) -> bool {
+        -MAP_LIMIT <= p.x && p.x <= MAX_X + MAP_LIMIT && -MAP_LIMIT <= p.y && p.y <= MAX_Y + MAP_LIMIT
     }
 
     fn move_monster(&mut self) {
@@ -1363,13 +1734,22 @@ impl Simulator {
                         .map(|p| Self::calculate_real_target(&m.component.position, &p, MAX_MONSTER_VELOCITY))
                         .fold(Point::new(), |l, r| l + r)
                         / m.component.control_move_target.len() as i32;
+                    m.component.position = m.component.position + averaged_target;
+                    // 以降も control された方向に進み続ける
+                    if averaged_target.norm2() > 0 {
+                        m.component.velocity =
+                            (averaged_target.to_f64().normalize() * MAX_MONSTER_VELOCITY as f64).to::<i32>();
+                    }
                 } else {
                     // 2. そうでなければ、設定された velocity を基に動く
                     m.component.position = m.component.next_pos();
                 }
             }
 
-            m.component.position = m.component.next_pos();
+            // base の近くにいたら、その base を狙う
+            if let Some(velocity) = Self::velocity_to_base(&m.component.position) {
+                m.component.velocity = velocity;
+            }
         }
     }
 
@@ -1407,11 +1787,13 @@ impl Simulator {
         // 8. SHIELD countdowns are decremented.
         self.countdown_shield();
 
-        // 9. New monsters appear. Dead monsters are removed.
+        // 9. Monsters reaching a base deal damage. New monsters appear. Dead monsters are removed.
+        self.attack_base();
         self.adjust_monster();
 
         for player_id in 0..2 {
             self.components.player_list[player_id].mana += mana_gain[player_id].all_mana;
+            self.components.player_list[player_id].wild_mana += mana_gain[player_id].wild_mana;
         }
 
         self.turn += 1;
@@ -1442,6 +1824,8 @@ impl Simulator {
             x: player_id * MAX_X,
             y: player_id * MAX_Y,
         };
+        board.player.health = self.components.player_list[player_id as usize].health;
+        board.player.mana = self.components.player_list[player_id as usize].mana;
 
         // opponent hero
         board.opponent.hero_list.clear();
@@ -1459,6 +1843,8 @@ impl Simulator {
             x: (1 - player_id) * MAX_X,
             y: (1 - player_id) * MAX_Y,
         };
+        board.opponent.health = self.components.player_list[1 - player_id as usize].health;
+        board.opponent.mana = self.components.player_list[1 - player_id as usize].mana;
 
         // monster
         board.monster_list.clear();
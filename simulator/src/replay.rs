@@ -0,0 +1,176 @@
+use crate::{Action, IPoint, Simulator};
+
+/// seed と両 player の action 列から、試合を再現するための記録
+///
+/// テキスト形式は以下の通りで、action は bot の出力と同じ書式 (座標は simulator 上の座標) で書く
+///
+/// ```text
+/// seed 0
+/// turn 0
+/// MOVE 5000 5000 player1 hero0
+/// ... (player1 の hero 3体分、player2 の hero 3体分の計6行)
+/// turn 1
+/// ...
+/// ```
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Replay {
+    pub seed: u64,
+    pub turn_list: Vec<[Vec<Action>; 2]>,
+}
+
+const HERO_SIZE: usize = 3;
+
+impl Replay {
+    pub fn new(seed: u64) -> Replay {
+        Replay {
+            seed,
+            turn_list: vec![],
+        }
+    }
+
+    pub fn push(&mut self, player_action: Vec<Action>, opponent_action: Vec<Action>) {
+        self.turn_list.push([player_action, opponent_action]);
+    }
+
+    /// turn ターン目の開始時点の盤面を再現する
+    pub fn simulate(&self, turn: usize) -> Simulator {
+        let mut sim = Simulator::new(self.seed);
+        for [player_action, opponent_action] in self.turn_list[..turn].iter() {
+            sim.next_state(player_action.clone(), opponent_action.clone());
+        }
+        sim
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut ret = format!("seed {}\n", self.seed);
+        for (turn, action_list) in self.turn_list.iter().enumerate() {
+            ret += &format!("turn {}\n", turn);
+            for action in action_list.iter().flat_map(|l| l.iter()) {
+                ret += &action.to_command();
+                ret += "\n";
+            }
+        }
+        ret
+    }
+
+    pub fn parse(text: &str) -> Result<Replay, String> {
+        let mut lines = text.lines().map(|l| l.trim()).filter(|l| !l.is_empty());
+
+        let seed = lines
+            .next()
+            .and_then(|l| l.strip_prefix("seed "))
+            .and_then(|s| s.trim().parse::<u64>().ok())
+            .ok_or("replay must start with `seed <number>`")?;
+        let mut ret = Replay::new(seed);
+
+        while let Some(line) = lines.next() {
+            if line != format!("turn {}", ret.turn_list.len()) {
+                return Err(format!("expected `turn {}`, but found `{}`", ret.turn_list.len(), line));
+            }
+            let mut action_list = vec![];
+            for _ in 0..2 * HERO_SIZE {
+                let line = lines.next().ok_or(format!(
+                    "turn {} has less than {} actions",
+                    ret.turn_list.len(),
+                    2 * HERO_SIZE
+                ))?;
+                action_list.push(Action::from_command(line).ok_or(format!("unknown action `{}`", line))?);
+            }
+            let opponent_action = action_list.split_off(HERO_SIZE);
+            ret.push(action_list, opponent_action);
+        }
+        Ok(ret)
+    }
+}
+
+impl Action {
+    /// ゲームに出力するコマンドの書式に変換する
+    pub fn to_command(&self) -> String {
+        let command = match self {
+            Action::Wait { message } => format!("WAIT {}", message),
+            Action::Move { point, message } => format!("MOVE {} {} {}", point.x, point.y, message),
+            Action::Wind { point, message } => format!("SPELL WIND {} {} {}", point.x, point.y, message),
+            Action::Shield { entity_id, message } => format!("SPELL SHIELD {} {}", entity_id, message),
+            Action::Control {
+                entity_id,
+                point,
+                message,
+            } => format!("SPELL CONTROL {} {} {} {}", entity_id, point.x, point.y, message),
+        };
+        command.trim_end().to_string()
+    }
+
+    /// to_command の逆変換
+    pub fn from_command(command: &str) -> Option<Action> {
+        let tokens = command.split_whitespace().collect::<Vec<_>>();
+        let int = |i: usize| tokens.get(i).and_then(|t| t.parse::<i32>().ok());
+        // 残りはメッセージ
+        let message = |from: usize| tokens.get(from..).map(|t| t.join(" ")).unwrap_or_default();
+
+        match tokens.as_slice() {
+            ["WAIT", ..] => Some(Action::Wait { message: message(1) }),
+            ["MOVE", ..] => Some(Action::Move {
+                point: IPoint { x: int(1)?, y: int(2)? },
+                message: message(3),
+            }),
+            ["SPELL", "WIND", ..] => Some(Action::Wind {
+                point: IPoint { x: int(2)?, y: int(3)? },
+                message: message(4),
+            }),
+            ["SPELL", "SHIELD", ..] => Some(Action::Shield {
+                entity_id: int(2)?,
+                message: message(3),
+            }),
+            ["SPELL", "CONTROL", ..] => Some(Action::Control {
+                entity_id: int(2)?,
+                point: IPoint { x: int(3)?, y: int(4)? },
+                message: message(5),
+            }),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::replay::Replay;
+    use crate::{Action, IPoint};
+
+    #[test]
+    fn test_text_round_trip() {
+        let mut replay = Replay::new(42);
+        replay.push(
+            vec![
+                Action::Wait { message: String::new() },
+                Action::Move {
+                    point: IPoint { x: 100, y: -20 },
+                    message: "[m2]go home".to_string(),
+                },
+                Action::Wind {
+                    point: IPoint { x: 3, y: 4 },
+                    message: String::new(),
+                },
+            ],
+            vec![
+                Action::Shield {
+                    entity_id: 7,
+                    message: "shield self!".to_string(),
+                },
+                Action::Control {
+                    entity_id: 12,
+                    point: IPoint { x: 17630, y: 9000 },
+                    message: "[as]control".to_string(),
+                },
+                Action::Wait {
+                    message: "empty".to_string(),
+                },
+            ],
+        );
+
+        let parsed = Replay::parse(&replay.to_text()).unwrap();
+        assert_eq!(parsed.seed, 42);
+        assert_eq!(parsed.to_text(), replay.to_text());
+        assert!(Replay::parse("seed 1\nturn 0\nMOVE 1 2\n").is_err());
+    }
+}
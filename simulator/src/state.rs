@@ -0,0 +1,614 @@
+//! 探索用の軽い盤面
+//!
+//! Simulator と同じルールで進むが、Copy できるように固定長の配列だけで持つ
+//! 乱数の table は CachedRandom を参照で共有して、位置だけを持つ
+
+use crate::{
+    Action, CachedRandom, FPoint, IPoint, Number, Point, Random, Simulator, SpawnLocation, CENTER,
+    CONTROL_EFFECTIVE_RADIUS, HERO_ATTACK_RADIUS, MANA_GAIN_TO_ATTACK, MANA_TO_SPELL, MAX_HERO_VELOCITY,
+    MAX_MONSTER_VELOCITY, SHIELD_EFFECTIVE_TURN, WIND_DISTANCE, WIND_EFFECTIVE_RADIUS,
+};
+use crate::{BASE_ATTACK_RADIUS, BASE_ATTRACTION_RADIUS};
+
+pub const HERO_SIZE: usize = 3;
+/// 同時に存在できる monster の数 (sudden death 中でも足りる大きさ)
+pub const MAX_MONSTER: usize = 96;
+
+const ZERO: IPoint = Point { x: 0, y: 0 };
+
+/// table を共有して、位置だけを持つ乱数
+#[derive(Debug, Clone, Copy)]
+pub struct SharedRandom<'a> {
+    table: &'a CachedRandom,
+    index: usize,
+}
+
+impl<'a> SharedRandom<'a> {
+    /// index は table の大きさで丸める
+    pub fn new(table: &'a CachedRandom, index: usize) -> SharedRandom<'a> {
+        SharedRandom {
+            table,
+            index: index % table.len(),
+        }
+    }
+
+    fn update(&mut self) {
+        self.index += 1;
+        if self.index == self.table.len() {
+            self.index = 0;
+        }
+    }
+}
+
+impl Random for SharedRandom<'_> {
+    fn next_int(&mut self) -> u32 {
+        let ret = self.table.int_table[self.index];
+        self.update();
+        ret
+    }
+
+    fn next_float(&mut self) -> f64 {
+        let ret = self.table.uniform_table[self.index];
+        self.update();
+        ret
+    }
+}
+
+/// Action から message を除いて、Copy にしたもの
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Command {
+    Wait,
+    Move(IPoint),
+    Wind(IPoint),
+    Shield(i32),
+    Control(i32, IPoint),
+}
+
+impl Command {
+    pub fn to_action(self) -> Action {
+        let message = String::new();
+        match self {
+            Command::Wait => Action::Wait { message },
+            Command::Move(point) => Action::Move { point, message },
+            Command::Wind(point) => Action::Wind { point, message },
+            Command::Shield(entity_id) => Action::Shield { entity_id, message },
+            Command::Control(entity_id, point) => Action::Control {
+                entity_id,
+                point,
+                message,
+            },
+        }
+    }
+}
+
+impl From<&Action> for Command {
+    fn from(action: &Action) -> Command {
+        match *action {
+            Action::Wait { .. } => Command::Wait,
+            Action::Move { point, .. } => Command::Move(point),
+            Action::Wind { point, .. } => Command::Wind(point),
+            Action::Shield { entity_id, .. } => Command::Shield(entity_id),
+            Action::Control { entity_id, point, .. } => Command::Control(entity_id, point),
+        }
+    }
+}
+
+/// hero と monster に共通する部分
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Entity {
+    pub id: i32,
+    pub position: IPoint,
+    pub velocity: IPoint,
+    pub shield_life: i32,
+    // このターンにかかった control による移動量の和と数 (monster はこの平均だけ動く)
+    control_sum: IPoint,
+    control_count: u8,
+    // このターンに wind で動かされる量
+    push: IPoint,
+    pushed: bool,
+}
+
+impl Entity {
+    pub fn new(id: i32, position: IPoint, velocity: IPoint, shield_life: i32) -> Entity {
+        Entity {
+            id,
+            position,
+            velocity,
+            shield_life,
+            control_sum: ZERO,
+            control_count: 0,
+            push: ZERO,
+            pushed: false,
+        }
+    }
+
+    pub fn is_controlled(&self) -> bool {
+        self.control_count > 0
+    }
+
+    fn clear(&mut self) {
+        self.control_sum = ZERO;
+        self.control_count = 0;
+        self.push = ZERO;
+        self.pushed = false;
+    }
+
+    fn push_by_wind(&mut self, hero_position: &IPoint, point: &IPoint) {
+        if self.position.in_range(hero_position, WIND_EFFECTIVE_RADIUS) && self.shield_life == 0 {
+            self.push =
+                self.push + ((*point - *hero_position).to_f64().normalize() * (WIND_DISTANCE as f64)).to::<i32>();
+            self.pushed = true;
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Monster {
+    pub entity: Entity,
+    pub health: i32,
+}
+
+const EMPTY_MONSTER: Monster = Monster {
+    entity: Entity {
+        id: -1,
+        position: ZERO,
+        velocity: ZERO,
+        shield_life: 0,
+        control_sum: ZERO,
+        control_count: 0,
+        push: ZERO,
+        pushed: false,
+    },
+    health: 0,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Player {
+    pub health: i32,
+    pub mana: i32,
+    pub wild_mana: i32,
+    pub base: IPoint,
+    pub hero_list: [Entity; HERO_SIZE],
+}
+
+/// Simulator の盤面を、clone して何度も進められるようにしたもの
+#[derive(Debug, Clone, Copy)]
+pub struct GameState<'a> {
+    pub player_list: [Player; 2],
+    pub turn: usize,
+    monster_list: [Monster; MAX_MONSTER],
+    monster_len: usize,
+    next_id: i32,
+    random: SharedRandom<'a>,
+}
+
+impl<'a> GameState<'a> {
+    /// monster は push_monster で追加する
+    pub fn new(player_list: [Player; 2], turn: usize, next_id: i32, random: SharedRandom<'a>) -> GameState<'a> {
+        GameState {
+            player_list,
+            turn,
+            monster_list: [EMPTY_MONSTER; MAX_MONSTER],
+            monster_len: 0,
+            next_id,
+            random,
+        }
+    }
+
+    /// table は sim の乱数と同じ seed, size で作ったもの (Simulator::random の clone など)
+    pub fn from_simulator(sim: &Simulator, table: &'a CachedRandom) -> GameState<'a> {
+        let entity = |c: &crate::Component| Entity {
+            control_count: c.control_move_target.len() as u8,
+            pushed: c.pushed,
+            ..Entity::new(c.id, c.position, c.velocity, c.shield_life)
+        };
+        let player = |p: &crate::Player| Player {
+            health: p.health,
+            mana: p.mana,
+            wild_mana: p.wild_mana,
+            base: p.base,
+            hero_list: [0, 1, 2].map(|hero_id| entity(&p.hero_list[hero_id].component)),
+        };
+
+        let mut ret = GameState::new(
+            [
+                player(&sim.components.player_list[0]),
+                player(&sim.components.player_list[1]),
+            ],
+            sim.turn,
+            sim.system.unique_id,
+            SharedRandom::new(table, sim.system.random.index),
+        );
+        for m in sim.components.monster_list.iter() {
+            ret.push_monster(Monster {
+                entity: entity(&m.component),
+                health: m.health,
+            });
+        }
+        ret
+    }
+
+    pub fn monster_list(&self) -> &[Monster] {
+        &self.monster_list[..self.monster_len]
+    }
+
+    /// MAX_MONSTER を超える分は無視する
+    pub fn push_monster(&mut self, monster: Monster) {
+        debug_assert!(self.monster_len < MAX_MONSTER);
+        if self.monster_len < MAX_MONSTER {
+            self.monster_list[self.monster_len] = monster;
+            self.monster_len += 1;
+        }
+    }
+
+    pub fn finish_game(&self) -> bool {
+        self.player_list[0].health == 0 || self.player_list[1].health == 0 || self.turn == 220
+    }
+
+    fn entity(&self, id: i32) -> Option<&Entity> {
+        self.player_list
+            .iter()
+            .flat_map(|p| p.hero_list.iter())
+            .chain(self.monster_list().iter().map(|m| &m.entity))
+            .find(|e| e.id == id)
+    }
+
+    fn entity_mut(&mut self, id: i32) -> Option<&mut Entity> {
+        let monster_len = self.monster_len;
+        self.player_list
+            .iter_mut()
+            .flat_map(|p| p.hero_list.iter_mut())
+            .chain(self.monster_list[..monster_len].iter_mut().map(|m| &mut m.entity))
+            .find(|e| e.id == id)
+    }
+
+    fn for_each_entity_mut(&mut self, mut f: impl FnMut(&mut Entity)) {
+        for player in self.player_list.iter_mut() {
+            for hero in player.hero_list.iter_mut() {
+                f(hero);
+            }
+        }
+        for monster in self.monster_list[..self.monster_len].iter_mut() {
+            f(&mut monster.entity);
+        }
+    }
+
+    /// spell の対象が hero_id の hero の射程内にいたら、マナを消費して対象を返す
+    /// (Simulator では射程外への spell は違反だが、ここでは何もしない)
+    fn spell_target(&mut self, player_id: usize, hero_id: usize, entity_id: i32) -> Option<&mut Entity> {
+        let hero_position = self.player_list[player_id].hero_list[hero_id].position;
+        let in_range = self
+            .entity(entity_id)
+            .map(|e| hero_position.in_range(&e.position, CONTROL_EFFECTIVE_RADIUS))?;
+        if self.player_list[player_id].mana < MANA_TO_SPELL || !in_range {
+            return None;
+        }
+        self.player_list[player_id].mana -= MANA_TO_SPELL;
+        self.entity_mut(entity_id)
+    }
+
+    fn do_control(&mut self, command_list: &[[Command; HERO_SIZE]; 2]) {
+        for (player_id, command_list) in command_list.iter().enumerate() {
+            for (hero_id, command) in command_list.iter().enumerate() {
+                if let Command::Control(entity_id, point) = *command {
+                    if let Some(target) = self.spell_target(player_id, hero_id, entity_id) {
+                        if target.shield_life == 0 {
+                            // monster の移動前に位置が変わることはないので、ここで移動量を決めて良い
+                            target.control_sum = target.control_sum
+                                + Simulator::calculate_real_target(&target.position, &point, MAX_MONSTER_VELOCITY);
+                            target.control_count += 1;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn do_shield(&mut self, command_list: &[[Command; HERO_SIZE]; 2]) {
+        for (player_id, command_list) in command_list.iter().enumerate() {
+            for (hero_id, command) in command_list.iter().enumerate() {
+                if let Command::Shield(entity_id) = *command {
+                    if let Some(target) = self.spell_target(player_id, hero_id, entity_id) {
+                        // shield 有効の場合、shield 魔法自体も無効
+                        if target.shield_life == 0 {
+                            // 最後に decrease する分も足しておく
+                            target.shield_life = SHIELD_EFFECTIVE_TURN + 1;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn move_hero(&mut self, command_list: &[[Command; HERO_SIZE]; 2]) {
+        for (player, command_list) in self.player_list.iter_mut().zip(command_list.iter()) {
+            for (hero, command) in player.hero_list.iter_mut().zip(command_list.iter()) {
+                if let Command::Move(point) = command {
+                    hero.velocity = Simulator::calculate_real_target(&hero.position, point, MAX_HERO_VELOCITY);
+                    hero.position = Simulator::snap_to_game_zone(hero.position + hero.velocity);
+                }
+            }
+        }
+    }
+
+    /// 各 player の (全てのマナ, wild mana) の増分
+    fn attack_monster(&mut self) -> [(i32, i32); 2] {
+        let mut mana_gain = [(0, 0); 2];
+        let player_list = &self.player_list;
+        for monster in self.monster_list[..self.monster_len].iter_mut() {
+            for (player_id, player) in player_list.iter().enumerate() {
+                for hero in player.hero_list.iter() {
+                    if monster.entity.position.in_range(&hero.position, HERO_ATTACK_RADIUS) {
+                        monster.health -= MANA_GAIN_TO_ATTACK;
+                        mana_gain[player_id].0 += MANA_GAIN_TO_ATTACK;
+                        if !player.base.in_range(&monster.entity.position, BASE_ATTRACTION_RADIUS) {
+                            mana_gain[player_id].1 += MANA_GAIN_TO_ATTACK;
+                        }
+                    }
+                }
+            }
+        }
+        mana_gain
+    }
+
+    fn do_wind(&mut self, command_list: &[[Command; HERO_SIZE]; 2]) {
+        for (player_id, command_list) in command_list.iter().enumerate() {
+            for (hero_id, command) in command_list.iter().enumerate() {
+                if let Command::Wind(point) = *command {
+                    if self.player_list[player_id].mana < MANA_TO_SPELL {
+                        continue;
+                    }
+                    self.player_list[player_id].mana -= MANA_TO_SPELL;
+
+                    // 自分の hero 以外 (移動は全員分が決まってから)
+                    let hero_position = self.player_list[player_id].hero_list[hero_id].position;
+                    for hero in self.player_list[1 - player_id].hero_list.iter_mut() {
+                        hero.push_by_wind(&hero_position, &point);
+                    }
+                    for monster in self.monster_list[..self.monster_len].iter_mut() {
+                        monster.entity.push_by_wind(&hero_position, &point);
+                    }
+                }
+            }
+        }
+
+        for player in self.player_list.iter_mut() {
+            for hero in player.hero_list.iter_mut().filter(|h| h.pushed) {
+                hero.position = Simulator::snap_to_game_zone(hero.position + hero.push);
+            }
+        }
+        // 乱数を使う順番を Simulator (id 順) と揃える
+        for monster in self.monster_list[..self.monster_len].iter_mut() {
+            let entity = &mut monster.entity;
+            if !entity.pushed {
+                continue;
+            }
+            let p = entity.position;
+            let np = p + entity.push;
+            if Simulator::go_outside_around_base(&p, &np) {
+                entity.position = Simulator::snap_to_game_zone(np);
+            } else {
+                entity.position = np;
+                if Simulator::go_out_from_base(&p, &np) {
+                    entity.velocity = Simulator::random_velocity(&mut self.random, MAX_MONSTER_VELOCITY);
+                }
+            }
+        }
+    }
+
+    fn move_monster(&mut self) {
+        for monster in self.monster_list[..self.monster_len].iter_mut() {
+            let entity = &mut monster.entity;
+            // 既に盤外に出ていたら無視
+            if !Simulator::is_in_gameboard(&entity.position) {
+                continue;
+            }
+
+            if !entity.pushed && monster.health != 0 {
+                if entity.is_controlled() {
+                    let averaged_target = entity.control_sum / entity.control_count as i32;
+                    entity.position = entity.position + averaged_target;
+                    if averaged_target.norm2() > 0 {
+                        entity.velocity =
+                            (averaged_target.to_f64().normalize() * MAX_MONSTER_VELOCITY as f64).to::<i32>();
+                    }
+                } else {
+                    entity.position = entity.position + entity.velocity;
+                }
+            }
+
+            if let Some(velocity) = Simulator::velocity_to_base(&entity.position) {
+                entity.velocity = velocity;
+            }
+        }
+    }
+
+    fn attack_base(&mut self) {
+        for monster in self.monster_list[..self.monster_len]
+            .iter_mut()
+            .filter(|m| m.health > 0)
+        {
+            for player in self.player_list.iter_mut() {
+                if player.base.in_range(&monster.entity.position, BASE_ATTACK_RADIUS) {
+                    player.health = Number::max(0, player.health - 1);
+                    monster.health = 0;
+                    break;
+                }
+            }
+        }
+    }
+
+    fn adjust_monster(&mut self) {
+        // remove dead monster
+        let mut len = 0;
+        for i in 0..self.monster_len {
+            let monster = self.monster_list[i];
+            if Simulator::monster_remains(monster.health, &monster.entity.position) {
+                self.monster_list[len] = monster;
+                len += 1;
+            }
+        }
+        self.monster_len = len;
+
+        // appear new monster
+        let mut spawn_list = [(FPoint::new(), ZERO); 2];
+        let mut spawn_len = 0;
+        Simulator::spawn(
+            self.turn,
+            &SpawnLocation::list(),
+            &mut self.random,
+            |position, velocity| {
+                spawn_list[spawn_len] = (position, velocity);
+                spawn_len += 1;
+            },
+        );
+        for &(position, velocity) in spawn_list[..spawn_len].iter() {
+            self.create_monster(position, velocity);
+        }
+    }
+
+    // 点対称な位置にも同時に作る
+    fn create_monster(&mut self, position: FPoint, velocity: IPoint) {
+        let health = crate::Monster::max_health(self.turn as i32 / 5);
+        for (position, velocity) in [
+            (position, velocity),
+            (position.point_symmetry(&CENTER.to_f64()), velocity.flip()),
+        ] {
+            let id = self.next_id;
+            self.next_id += 1;
+            self.push_monster(Monster {
+                entity: Entity::new(id, position.to::<i32>(), velocity, 0),
+                health,
+            });
+        }
+    }
+
+    /// Simulator::next_state と同じ順番で 1ターン進める
+    pub fn next_state(&mut self, command_list: &[[Command; HERO_SIZE]; 2]) {
+        self.for_each_entity_mut(|e| e.clear());
+
+        self.do_control(command_list);
+        self.do_shield(command_list);
+        self.move_hero(command_list);
+        let mana_gain = self.attack_monster();
+        self.do_wind(command_list);
+        self.move_monster();
+        self.for_each_entity_mut(|e| e.shield_life = Number::max(0, e.shield_life - 1));
+        self.attack_base();
+        self.adjust_monster();
+
+        for (player, (all_mana, wild_mana)) in self.player_list.iter_mut().zip(mana_gain) {
+            player.mana += all_mana;
+            player.wild_mana += wild_mana;
+        }
+
+        self.turn += 1;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::state::{Command, GameState, HERO_SIZE};
+    use crate::{IPoint, Simulator, XorShift, CONTROL_EFFECTIVE_RADIUS, MAX_X, MAX_Y, WIND_EFFECTIVE_RADIUS};
+
+    /// spell も使う適当な手を決める
+    fn random_command_list(sim: &Simulator, rand: &mut XorShift) -> [[Command; HERO_SIZE]; 2] {
+        let mut ret = [[Command::Wait; HERO_SIZE]; 2];
+        for (player_id, player) in sim.components.player_list.iter().enumerate() {
+            for (hero_id, hero) in player.hero_list.iter().enumerate() {
+                let position = hero.component.position;
+                let point = IPoint {
+                    x: (rand.next() % MAX_X as u64) as i32,
+                    y: (rand.next() % MAX_Y as u64) as i32,
+                };
+                let near = |radius| {
+                    sim.components
+                        .monster_list
+                        .iter()
+                        .find(|m| m.component.position.in_range(&position, radius))
+                        .map(|m| m.component.id)
+                };
+
+                ret[player_id][hero_id] = match rand.next() % 6 {
+                    0 if near(WIND_EFFECTIVE_RADIUS).is_some() && point != position => Command::Wind(point),
+                    1 => near(CONTROL_EFFECTIVE_RADIUS).map_or(Command::Wait, |id| Command::Control(id, point)),
+                    2 => near(CONTROL_EFFECTIVE_RADIUS).map_or(Command::Wait, Command::Shield),
+                    3 => Command::Wait,
+                    _ => Command::Move(point),
+                };
+            }
+        }
+        ret
+    }
+
+    fn assert_same_state(sim: &Simulator, state: &GameState) {
+        assert_eq!(sim.turn, state.turn);
+        for (player, state_player) in sim.components.player_list.iter().zip(state.player_list.iter()) {
+            assert_eq!(
+                (player.health, player.mana, player.wild_mana),
+                (state_player.health, state_player.mana, state_player.wild_mana)
+            );
+            for (hero, state_hero) in player.hero_list.iter().zip(state_player.hero_list.iter()) {
+                let c = &hero.component;
+                assert_eq!(
+                    (c.id, c.position, c.velocity, c.shield_life, c.is_controlled()),
+                    (
+                        state_hero.id,
+                        state_hero.position,
+                        state_hero.velocity,
+                        state_hero.shield_life,
+                        state_hero.is_controlled()
+                    )
+                );
+            }
+        }
+        assert_eq!(sim.components.monster_list.len(), state.monster_list().len());
+        for (monster, state_monster) in sim.components.monster_list.iter().zip(state.monster_list()) {
+            let c = &monster.component;
+            let e = &state_monster.entity;
+            assert_eq!(
+                (
+                    c.id,
+                    c.position,
+                    c.velocity,
+                    c.shield_life,
+                    c.is_controlled(),
+                    monster.health
+                ),
+                (
+                    e.id,
+                    e.position,
+                    e.velocity,
+                    e.shield_life,
+                    e.is_controlled(),
+                    state_monster.health
+                )
+            );
+        }
+    }
+
+    #[test]
+    fn test_same_as_simulator() {
+        for seed in 0..4 {
+            let mut sim = Simulator::new(seed);
+            let table = sim.random().clone();
+            let mut state = GameState::from_simulator(&sim, &table);
+            let mut rand = XorShift::new(seed + 100);
+
+            while !sim.finish_game() {
+                let command_list = random_command_list(&sim, &mut rand);
+                let action_list = command_list.map(|l| l.iter().map(|c| c.to_action()).collect::<Vec<_>>());
+                let [player_action, opponent_action] = action_list;
+                sim.next_state(player_action, opponent_action);
+                state.next_state(&command_list);
+                assert_same_state(&sim, &state);
+
+                // 途中から作り直しても同じ
+                if sim.turn.is_multiple_of(50) {
+                    state = GameState::from_simulator(&sim, &table);
+                }
+            }
+            assert!(state.finish_game());
+        }
+    }
+}
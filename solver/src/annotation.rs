@@ -0,0 +1,42 @@
+use simulator::inout::Point;
+use simulator::CENTER;
+
+/// visualizer に描画させるための、solver 内部の判断材料
+/// 座標は solver が受け取った board の座標系 (自陣が左上) で表す
+#[derive(Clone, Debug)]
+pub enum Annotation {
+    /// 攻撃範囲・WIND 範囲などの円
+    Circle { center: Point, radius: i32, label: String },
+    /// 移動先・spell の対象などへの線分
+    Line { from: Point, to: Point, label: String },
+    /// 評価値付きの候補点
+    Candidate { point: Point, score: i32, label: String },
+    /// hero に割り当てられている role
+    Role { hero_id: usize, role: String },
+    /// SolverState などの内部状態
+    Text { text: String },
+}
+
+impl Annotation {
+    /// 点対称な座標系に変換する (相手側 solver の出力を盤面に重ねるため)
+    pub fn point_symmetry(&self) -> Annotation {
+        match self {
+            Annotation::Circle { center, radius, label } => Annotation::Circle {
+                center: center.point_symmetry(&CENTER),
+                radius: *radius,
+                label: label.clone(),
+            },
+            Annotation::Line { from, to, label } => Annotation::Line {
+                from: from.point_symmetry(&CENTER),
+                to: to.point_symmetry(&CENTER),
+                label: label.clone(),
+            },
+            Annotation::Candidate { point, score, label } => Annotation::Candidate {
+                point: point.point_symmetry(&CENTER),
+                score: *score,
+                label: label.clone(),
+            },
+            Annotation::Role { .. } | Annotation::Text { .. } => self.clone(),
+        }
+    }
+}
@@ -0,0 +1,250 @@
+//! 防衛する hero と、自陣に向かってくる monster の割り当て
+//!
+//! hero 毎に「どの monster を追うか (もしくは追わないか)」の組み合わせ (M+1)^H 通りを全部試して、
+//! horizon 内に base が受けるダメージが一番少ないものを選ぶ
+//! 倒しきれない monster は、追いついている hero が wind で飛ばせるならダメージを受けないものとする
+//! 次のターンに base に入る monster を追っている hero は、予定通りに殴りきれなかった時に備えて、
+//! 1撃で倒せない monster が wind の範囲にいれば飛ばす
+
+use simulator::inout::*;
+use simulator::{MANA_GAIN_TO_ATTACK, MANA_TO_SPELL, WIND_EFFECTIVE_RADIUS};
+
+use crate::geometry::MonsterPath;
+
+// 全探索するので、到着の早いものからこの数だけ考える
+const MAX_TARGET: usize = 6;
+
+// base にダメージを受けるのが一番重い
+const DAMAGE_COST: i32 = 1000;
+const WIND_COST: i32 = 100;
+// マナ集めをしている hero を防衛に回すコスト
+const HELPER_COST: i32 = 50;
+// 防衛役が何かを追っているなら、マナが稼げるので少し良い
+const ASSIGN_BONUS: i32 = 30;
+
+/// 割り当ての対象になる hero
+#[derive(Clone, Copy, Debug)]
+pub struct Defender {
+    pub hero_id: usize,
+    /// 防衛が役目の hero か (false ならマナ集めを中断させることになる)
+    pub dedicated: bool,
+}
+
+/// hero 毎の割り当て結果
+#[derive(Clone, Debug, Default)]
+pub struct Assignment {
+    /// 追う monster の id
+    pub target: Vec<Option<i32>>,
+    /// 追いつける位置 (この位置にいる monster を殴れる)
+    pub intercept: Vec<Option<Point>>,
+    /// 倒しきれずに wind で飛ばす予定の monster の id (飛ばす役の hero 毎)
+    pub wind_target: Vec<Option<i32>>,
+    /// この割り当てで base が受けると予想されるダメージ
+    pub damage: i32,
+}
+
+impl Assignment {
+    pub fn target(&self, hero_id: usize) -> Option<i32> {
+        self.target.get(hero_id).copied().flatten()
+    }
+
+    pub fn intercept(&self, hero_id: usize) -> Option<Point> {
+        self.intercept.get(hero_id).copied().flatten()
+    }
+
+    pub fn wind_target(&self, hero_id: usize) -> Option<i32> {
+        self.wind_target.get(hero_id).copied().flatten()
+    }
+}
+
+struct Threat<'a> {
+    monster: &'a Monster,
+    path: MonsterPath,
+    // base に入ってダメージを与えるターン
+    arrival: usize,
+}
+
+/// defender_list の hero に、自陣に向かってくる monster を割り当てる
+pub fn assign(board: &Board, defender_list: &[Defender]) -> Assignment {
+    let mut threat_list = board
+        .monster_list
+        .iter()
+        .filter_map(|m| {
+            let path = MonsterPath::new(board, m);
+            let arrival = path.arrival?;
+            Some(Threat {
+                monster: m,
+                path,
+                arrival,
+            })
+        })
+        .collect::<Vec<_>>();
+    threat_list.sort_by_key(|t| t.arrival);
+    threat_list.truncate(MAX_TARGET);
+
+    let hero_size = board.player.hero_list.len();
+    let mut best = Assignment {
+        target: vec![None; hero_size],
+        intercept: vec![None; hero_size],
+        wind_target: vec![None; hero_size],
+        damage: 0,
+    };
+    if threat_list.is_empty() || defender_list.is_empty() {
+        return best;
+    }
+
+    // intercept[d][t]: defender d が threat t に追いつくターンと位置
+    let intercept = defender_list
+        .iter()
+        .map(|d| {
+            let hero = &board.player.hero_list[d.hero_id];
+            threat_list
+                .iter()
+                .map(|t| t.path.intercept(&hero.pos))
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    let wind_limit = board.player.mana / MANA_TO_SPELL;
+    let choice_size = threat_list.len() + 1;
+    let mut best_cost = i32::MAX;
+    let mut choice = vec![0; defender_list.len()];
+    for code in 0..choice_size.pow(defender_list.len() as u32) {
+        // choice[d] == threat_list.len() は、何も追わない
+        let mut rest = code;
+        for c in choice.iter_mut() {
+            *c = rest % choice_size;
+            rest /= choice_size;
+        }
+
+        let mut cost = 0;
+        let mut chaser_list = vec![vec![]; threat_list.len()];
+        let mut chaser_id_list = vec![vec![]; threat_list.len()];
+        for (d, (defender, &c)) in defender_list.iter().zip(choice.iter()).enumerate() {
+            if c == threat_list.len() {
+                continue;
+            }
+            match intercept[d][c] {
+                Some(i) => {
+                    chaser_list[c].push(board.player.hero_list[defender.hero_id].pos);
+                    chaser_id_list[c].push(defender.hero_id);
+                    cost += i.turn as i32;
+                    cost += if defender.dedicated { -ASSIGN_BONUS } else { HELPER_COST };
+                }
+                // 追いつけないのに追うのは無駄
+                None => cost += DAMAGE_COST,
+            }
+        }
+
+        // 倒しきれないものは、到着の早い順に追っている hero の 1体が wind で飛ばす
+        let mut damage = 0;
+        let mut wind_target = vec![None; hero_size];
+        let mut wind_count = 0;
+        for (i, t) in threat_list.iter().enumerate() {
+            if t.path.kill_turn(t.monster.health, &chaser_list[i]).is_some() {
+                continue;
+            }
+            let winder = chaser_id_list[i]
+                .iter()
+                .copied()
+                .find(|&hero_id| wind_target[hero_id].is_none());
+            let can_wind = (t.monster.shield_life as usize) < t.arrival;
+            if let Some(hero_id) = winder.filter(|_| can_wind && wind_count < wind_limit) {
+                wind_target[hero_id] = Some(t.monster.id);
+                wind_count += 1;
+                cost += WIND_COST;
+            } else {
+                damage += 1;
+                cost += DAMAGE_COST;
+            }
+        }
+
+        if cost < best_cost {
+            best_cost = cost;
+            best.damage = damage;
+            best.wind_target = wind_target;
+            best.target = vec![None; hero_size];
+            best.intercept = vec![None; hero_size];
+            for (d, (defender, &c)) in defender_list.iter().zip(choice.iter()).enumerate() {
+                if c < threat_list.len() {
+                    best.target[defender.hero_id] = Some(threat_list[c].monster.id);
+                    best.intercept[defender.hero_id] = intercept[d][c].map(|i| i.point);
+                }
+            }
+        }
+    }
+
+    // 次のターンに base に入る monster を追っていて、1撃で倒せない monster が wind の範囲にいるなら飛ばす
+    for d in defender_list.iter() {
+        let hero = &board.player.hero_list[d.hero_id];
+        let imminent = best.target[d.hero_id]
+            .and_then(|id| threat_list.iter().find(|t| t.monster.id == id))
+            .filter(|t| t.arrival <= 1);
+        let crowded = board
+            .monster_list
+            .iter()
+            .any(|m| hero.pos.in_range(&m.pos, WIND_EFFECTIVE_RADIUS) && m.health > MANA_GAIN_TO_ATTACK);
+        if let Some(t) = imminent.filter(|_| crowded && best.wind_target[d.hero_id].is_none()) {
+            best.wind_target[d.hero_id] = Some(t.monster.id);
+        }
+    }
+    best
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use simulator::{MAX_X, MAX_Y};
+
+    fn monster(id: i32, pos: Point, health: i32) -> Monster {
+        Monster {
+            id,
+            pos,
+            shield_life: 0,
+            is_controlled: false,
+            health,
+            v: ((Point { x: 0, y: 0 } - pos).to_f64().normalize() * 400.0).to::<i32>(),
+            threat_state: MonsterThreatState::PlayerThreat,
+        }
+    }
+
+    #[test]
+    fn test_split_targets() {
+        let mut board = Board {
+            player: Player::new(),
+            opponent: Player::new(),
+            monster_list: vec![
+                monster(10, Point { x: 4000, y: 1000 }, 14),
+                monster(11, Point { x: 1000, y: 4000 }, 14),
+            ],
+            turn: 50,
+        };
+        board.opponent.base = Point { x: MAX_X, y: MAX_Y };
+        board.player.hero_list = [
+            Point { x: 3000, y: 1500 },
+            Point { x: 2800, y: 2000 },
+            Point { x: 9000, y: 4500 },
+        ]
+        .iter()
+        .enumerate()
+        .map(|(id, &pos)| Hero {
+            id: id as i32,
+            pos,
+            shield_life: 0,
+            is_controlled: false,
+        })
+        .collect();
+
+        // 2体とも一番近いのは monster 10 だが、それぞれ別の monster を追う
+        let defender_list = [0, 1].map(|hero_id| Defender {
+            hero_id,
+            dedicated: true,
+        });
+        let assignment = assign(&board, &defender_list);
+        assert_eq!(assignment.damage, 0);
+        let mut target_list = [assignment.target(0), assignment.target(1)];
+        target_list.sort();
+        assert_eq!(target_list, [Some(10), Some(11)]);
+        assert_eq!(assignment.target(2), None);
+    }
+}
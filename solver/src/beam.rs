@@ -0,0 +1,317 @@
+//! 3体の hero の action の組をまとめて選ぶ beam search
+//!
+//! hero 毎に候補の action (マナが稼げる位置や monster への移動と、近くの対象への spell) を作り、
+//! その直積を ForwardModel の上で数ターン進めて、base の health, マナ, base への脅威で評価する
+//! 相手 hero は、相手陣に近い monster を殴りに行くもの (default_command_list) として動かす
+//!
+//! 役割毎の手書きの判断 (hint) も候補に入れるので、時間が足りなければ hint がそのまま残る
+
+use simulator::inout::*;
+use simulator::state::{Command, GameState, HERO_SIZE};
+use simulator::{BASE_ATTRACTION_RADIUS, CONTROL_EFFECTIVE_RADIUS, MANA_TO_SPELL, WIND_EFFECTIVE_RADIUS};
+
+use crate::farming::{self, Target};
+use crate::forward::{self, Belief, ForwardModel};
+
+// 何ターン先まで進めるか
+pub const DEPTH: usize = 3;
+const BEAM_WIDTH: usize = 16;
+// hero 1体あたりの候補の数 (最初のターンと、それより先)
+const ROOT_CANDIDATE: usize = 6;
+const DEEP_CANDIDATE: usize = 3;
+// 本物の seed は分からないので、適当な seed の乱数で monster を湧かせる
+const SEED: u64 = 0;
+// 相手陣の近くでこの距離以内の monster には、shield をかけて送り込む
+const SHIELD_RADIUS_FROM_BASE: i32 = BASE_ATTRACTION_RADIUS + 1000;
+
+// 評価の重み
+const HEALTH_WEIGHT: i32 = 10000;
+const MANA_WEIGHT: i32 = 10;
+const WILD_MANA_WEIGHT: i32 = 5;
+// base の引力圏の中で、base に 1 近づく毎の脅威
+const THREAT_WEIGHT: i32 = 1;
+
+#[derive(Clone)]
+pub struct BeamSearch {
+    model: ForwardModel,
+}
+
+#[derive(Clone, Copy)]
+struct Node<'a> {
+    state: GameState<'a>,
+    // 最初のターンに選んだ action の組
+    first: [Command; HERO_SIZE],
+    score: i32,
+}
+
+impl BeamSearch {
+    pub fn new() -> BeamSearch {
+        BeamSearch {
+            model: ForwardModel::new(SEED),
+        }
+    }
+
+    /// 一番評価の良い action の組
+    ///
+    /// hint より良いものが見つからない時や、1ターン目を調べきる前に is_time_over になった時は hint を返す
+    pub fn search(
+        &self,
+        board: &Board,
+        belief: &Belief,
+        hint: &[Action],
+        is_time_over: impl Fn() -> bool,
+    ) -> Vec<Action> {
+        let hint_command = forward::to_command_list(hint);
+        let root = self.model.determinize(board, belief, board.turn);
+
+        let mut beam: Vec<Node<'_>> = vec![];
+        'depth: for depth in 0..DEPTH {
+            let parent_list = if depth == 0 {
+                vec![Node {
+                    state: root,
+                    first: hint_command,
+                    score: 0,
+                }]
+            } else {
+                beam.clone()
+            };
+
+            let mut next = vec![];
+            for parent in parent_list.iter() {
+                // 調べきれなかった深さは使わない
+                if is_time_over() {
+                    break 'depth;
+                }
+                let hint = if depth == 0 { Some(&hint_command) } else { None };
+                let opponent = default_command_list(&parent.state, 1);
+                for command_list in joint_candidate_list(&parent.state, 0, hint) {
+                    let mut state = parent.state;
+                    state.next_state(&[command_list, opponent]);
+                    next.push(Node {
+                        state,
+                        first: if depth == 0 { command_list } else { parent.first },
+                        score: evaluate(&state),
+                    });
+                }
+            }
+            // 同じ評価なら hint を残す
+            next.sort_by_key(|node| (std::cmp::Reverse(node.score), node.first != hint_command));
+            next.truncate(BEAM_WIDTH);
+            beam = next;
+        }
+
+        match beam.first() {
+            Some(best) if best.first != hint_command => best
+                .first
+                .iter()
+                .map(|command| with_message(command.to_action(), format!("[beam]{}", best.score)))
+                .collect(),
+            _ => hint.to_vec(),
+        }
+    }
+}
+
+impl Default for BeamSearch {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// player_id の hero 毎の候補の直積
+///
+/// hint がある時は hero 1体あたり ROOT_CANDIDATE 個、無い時は DEEP_CANDIDATE 個まで
+pub(crate) fn joint_candidate_list(
+    state: &GameState,
+    player_id: usize,
+    hint: Option<&[Command; HERO_SIZE]>,
+) -> Vec<[Command; HERO_SIZE]> {
+    let limit = if hint.is_some() { ROOT_CANDIDATE } else { DEEP_CANDIDATE };
+    let mut ret = vec![[Command::Wait; HERO_SIZE]];
+    for hero_id in 0..HERO_SIZE {
+        let candidate_list = candidate_list(state, player_id, hero_id, hint.map(|h| h[hero_id]), limit);
+        ret = ret
+            .iter()
+            .flat_map(|command_list| {
+                candidate_list.iter().map(move |&command| {
+                    let mut command_list = *command_list;
+                    command_list[hero_id] = command;
+                    command_list
+                })
+            })
+            .collect();
+    }
+    ret
+}
+
+/// player_id の hero 1体の候補を、limit 個まで
+fn candidate_list(
+    state: &GameState,
+    player_id: usize,
+    hero_id: usize,
+    hint: Option<Command>,
+    limit: usize,
+) -> Vec<Command> {
+    let player = &state.player_list[player_id];
+    let opponent = &state.player_list[1 - player_id];
+    let hero = &player.hero_list[hero_id];
+    let monster_list = state.monster_list().iter().filter(|m| m.health > 0).collect::<Vec<_>>();
+    let nearest = |filter: &dyn Fn(&&simulator::state::Monster) -> bool| {
+        monster_list
+            .iter()
+            .filter(|m| filter(m))
+            .min_by_key(|m| m.entity.position.distance2(&hero.position))
+            .copied()
+    };
+
+    let mut ret = vec![];
+    ret.extend(hint);
+
+    // 一番多く殴れる位置
+    let target_list = monster_list
+        .iter()
+        .map(|m| Target {
+            pos: m.entity.position,
+            weight: 1,
+        })
+        .collect::<Vec<_>>();
+    if let Some(h) = farming::hit_point_list(&hero.position, &target_list).first() {
+        ret.push(Command::Move(h.point));
+    }
+
+    // 自陣に向かってくる monster を追う
+    if let Some(m) = nearest(&|m| player.base.in_range(&m.entity.position, BASE_ATTRACTION_RADIUS)) {
+        ret.push(Command::Move(m.entity.position + m.entity.velocity));
+    }
+
+    if player.mana >= MANA_TO_SPELL {
+        // 近くの monster を相手陣の方に飛ばす
+        if nearest(&|m| m.entity.shield_life == 0 && hero.position.in_range(&m.entity.position, WIND_EFFECTIVE_RADIUS))
+            .is_some()
+        {
+            ret.push(Command::Wind(hero.position + (opponent.base - player.base)));
+        }
+        // 相手陣に向かっていない monster を、相手陣に向ける
+        if let Some(m) = nearest(&|m| {
+            m.entity.shield_life == 0
+                && hero.position.in_range(&m.entity.position, CONTROL_EFFECTIVE_RADIUS)
+                && !opponent.base.in_range(&m.entity.position, BASE_ATTRACTION_RADIUS)
+        }) {
+            ret.push(Command::Control(m.entity.id, opponent.base));
+        }
+        // 相手陣の近くの monster に shield をかけて、止められないようにする
+        if let Some(m) = nearest(&|m| {
+            m.entity.shield_life == 0
+                && hero.position.in_range(&m.entity.position, CONTROL_EFFECTIVE_RADIUS)
+                && opponent.base.in_range(&m.entity.position, SHIELD_RADIUS_FROM_BASE)
+        }) {
+            ret.push(Command::Shield(m.entity.id));
+        }
+    }
+
+    // 近くの monster に寄る
+    if let Some(m) = nearest(&|_| true) {
+        ret.push(Command::Move(m.entity.position + m.entity.velocity));
+    }
+    ret.push(Command::Wait);
+
+    let mut unique = vec![];
+    for command in ret {
+        if !unique.contains(&command) {
+            unique.push(command);
+        }
+    }
+    unique.truncate(limit);
+    unique
+}
+
+/// 簡単な方策: player_id の hero は、自陣の引力圏の中の一番近い monster を殴りに行く
+pub(crate) fn default_command_list(state: &GameState, player_id: usize) -> [Command; HERO_SIZE] {
+    let player = &state.player_list[player_id];
+    let mut ret = [Command::Wait; HERO_SIZE];
+    for (command, hero) in ret.iter_mut().zip(player.hero_list.iter()) {
+        if let Some(m) = state
+            .monster_list()
+            .iter()
+            .filter(|m| m.health > 0 && player.base.in_range(&m.entity.position, BASE_ATTRACTION_RADIUS))
+            .min_by_key(|m| m.entity.position.distance2(&hero.position))
+        {
+            *command = Command::Move(m.entity.position + m.entity.velocity);
+        }
+    }
+    ret
+}
+
+/// 自分 (player 0) から見た盤面の良さ
+pub(crate) fn evaluate(state: &GameState) -> i32 {
+    let player = &state.player_list[0];
+    let opponent = &state.player_list[1];
+    let threat = |base: &Point| -> i32 {
+        state
+            .monster_list()
+            .iter()
+            .filter(|m| m.health > 0)
+            .map(|m| (BASE_ATTRACTION_RADIUS - base.distance(&m.entity.position)).max(0))
+            .sum()
+    };
+    (player.health - opponent.health) * HEALTH_WEIGHT
+        + (player.mana - opponent.mana) * MANA_WEIGHT
+        + (player.wild_mana - opponent.wild_mana) * WILD_MANA_WEIGHT
+        + (threat(&opponent.base) - threat(&player.base)) * THREAT_WEIGHT
+}
+
+pub(crate) fn with_message(action: Action, message: String) -> Action {
+    match action {
+        Action::Wait { .. } => Action::Wait { message },
+        Action::Move { point, .. } => Action::Move { point, message },
+        Action::Wind { point, .. } => Action::Wind { point, message },
+        Action::Shield { entity_id, .. } => Action::Shield { entity_id, message },
+        Action::Control { entity_id, point, .. } => Action::Control {
+            entity_id,
+            point,
+            message,
+        },
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use simulator::{MAX_X, MAX_Y};
+
+    #[test]
+    fn test_wind_threat_away() {
+        let mut board = Board {
+            player: Player::new(),
+            opponent: Player::new(),
+            monster_list: vec![],
+            turn: 50,
+        };
+        board.player.health = 1;
+        board.player.mana = 50;
+        board.opponent.health = 3;
+        board.opponent.base = Point { x: MAX_X, y: MAX_Y };
+        // 次のターンに base に入る、倒しきれない monster
+        board.monster_list.push(Monster {
+            id: 10,
+            pos: Point { x: 400, y: 400 },
+            shield_life: 0,
+            is_controlled: false,
+            health: 20,
+            v: Point { x: -283, y: -283 },
+            threat_state: MonsterThreatState::PlayerThreat,
+        });
+        board.player.hero_list = (0..3)
+            .map(|id| Hero {
+                id,
+                pos: Point { x: 1000, y: 1000 },
+                shield_life: 0,
+                is_controlled: false,
+            })
+            .collect();
+
+        // 何もしなければ負けるので、hint の Wait より良い手 (wind) を選ぶ
+        let hint = vec![Action::Wait { message: String::new() }; 3];
+        let action_list = BeamSearch::new().search(&board, &Belief::default(), &hint, || false);
+        assert!(action_list.iter().any(|a| matches!(a, Action::Wind { .. })));
+    }
+}
@@ -0,0 +1,44 @@
+//! 選んだパラメータを、submit に埋め込む Rust の定数として書き出す
+//!
+//! ```text
+//! cargo run -p solver --features config --bin bake_params -- [params.toml | params.json] [--write submit/src/main.rs]
+//! ```
+//!
+//! ファイルを指定しなければ、環境変数 (SOLVER_PARAMS, SOLVER_<名前>) から読む
+//! --write を付けると、そのファイルの BAKE_BEGIN から BAKE_END までを置き換える (付けなければ標準出力に書く)
+
+use solver::params::{self, Params};
+
+fn main() {
+    if let Err(e) = run(std::env::args().skip(1).collect()) {
+        eprintln!("error: {}", e);
+        std::process::exit(1);
+    }
+}
+
+fn run(args: Vec<String>) -> Result<(), String> {
+    let mut path = None;
+    let mut write = None;
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--write" => write = Some(args.next().ok_or("missing value for `--write`")?),
+            _ => path = Some(arg),
+        }
+    }
+
+    let params = match path {
+        Some(path) => Params::load(&path)?,
+        None => Params::from_env()?,
+    };
+    let baked = params.bake();
+    match write {
+        Some(target) => {
+            let source = std::fs::read_to_string(&target).map_err(|e| format!("failed to open {}: {}", target, e))?;
+            let source = params::replace_baked(&source, &baked)?;
+            std::fs::write(&target, source).map_err(|e| format!("failed to write {}: {}", target, e))?;
+        }
+        None => print!("{}", baked),
+    }
+    Ok(())
+}
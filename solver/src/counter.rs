@@ -0,0 +1,147 @@
+//! 相手の spell への備え
+//!
+//! 相手の hero は、今いる所から 2200 以内の hero を control で、こちらが動いた後に 1280 以内にいる hero を wind で動かせる
+//! 動かされると困る hero (monster を追っている最中など) は shield で包み、
+//! 手の空いている hero は wind の届かない所で待って、マナを使わずに避ける
+//!
+//! 相手の attacker は、自陣の視界 (6000) の外にいる monster を wind で押し込んでくる
+//! 押し込まれると気付いた時には引力圏の中にいるので、押し込まれる先に hero を 1体置いておく
+
+use simulator::inout::*;
+use simulator::{
+    BASE_ATTRACTION_RADIUS, MANA_TO_SPELL, MAX_HERO_VELOCITY, VISIBLE_RADIUS_FROM_BASE, WIND_DISTANCE,
+    WIND_EFFECTIVE_RADIUS,
+};
+
+use crate::forward::Belief;
+
+// wind の届く範囲から、これだけ余裕を持って離れる
+const DODGE_MARGIN: i32 = 100;
+
+/// 相手が wind で自陣に押し込めそうな monster
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WindThreat {
+    pub monster_id: i32,
+    /// 押し込まれた先
+    pub landing: Point,
+    /// 押し込める相手 hero の数
+    pub caster: usize,
+}
+
+/// hero が to に向かう時に、動いた後に相手 hero の wind が届かないようにずらした位置
+///
+/// shield がかかっている時と、相手が spell を撃てない時はずらさない
+pub fn dodge_point(board: &Board, hero: &Hero, to: Point, opponent_mana: i32) -> Point {
+    if hero.shield_life > 0 || opponent_mana < MANA_TO_SPELL {
+        return to;
+    }
+    let mut ret = to;
+    for h in board.opponent.hero_list.iter() {
+        if h.pos.in_range(&ret, WIND_EFFECTIVE_RADIUS) {
+            // 重なっている時は自陣の側に避ける
+            let dir = if ret == h.pos {
+                board.player.base - h.pos
+            } else {
+                ret - h.pos
+            };
+            ret = h.pos + (dir.to_f64().normalize() * (WIND_EFFECTIVE_RADIUS + DODGE_MARGIN) as f64).to::<i32>();
+        }
+    }
+    ret
+}
+
+/// 自陣の視界の外にいて、相手 hero に次のターンまでに wind で押されると自陣の引力圏に入る monster
+///
+/// 押し込まれた先が base に近い順
+pub fn wind_threat_list(board: &Board, belief: &Belief, opponent_mana: i32) -> Vec<WindThreat> {
+    let base = board.player.base;
+    let opponent_hero_list = board
+        .opponent
+        .hero_list
+        .iter()
+        .chain(belief.opponent_hero_list.iter())
+        .map(|h| h.pos)
+        .collect::<Vec<_>>();
+    let mut ret = board
+        .monster_list
+        .iter()
+        .chain(belief.monster_list.iter())
+        .filter(|m| !base.in_range(&m.pos, VISIBLE_RADIUS_FROM_BASE))
+        .filter_map(|m| {
+            // 撃てる回数より多くは押せない
+            let caster = opponent_hero_list
+                .iter()
+                .filter(|h| h.in_range(&m.pos, MAX_HERO_VELOCITY + WIND_EFFECTIVE_RADIUS))
+                .count()
+                .min((opponent_mana / MANA_TO_SPELL) as usize);
+            let push = (base - m.pos).to_f64().normalize() * (WIND_DISTANCE * caster as i32) as f64;
+            let landing = m.pos + push.to::<i32>();
+            if caster > 0 && base.in_range(&landing, BASE_ATTRACTION_RADIUS) {
+                Some(WindThreat {
+                    monster_id: m.id,
+                    landing,
+                    caster,
+                })
+            } else {
+                None
+            }
+        })
+        .collect::<Vec<_>>();
+    ret.sort_by_key(|t| t.landing.distance(&base));
+    ret
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_counter() {
+        let mut board = Board {
+            player: Player::new(),
+            opponent: Player::new(),
+            monster_list: vec![],
+            turn: 100,
+        };
+        let attacker = Hero {
+            id: 3,
+            pos: Point { x: 5500, y: 3000 },
+            shield_life: 0,
+            is_controlled: false,
+        };
+        board.opponent.hero_list.push(attacker.clone());
+        let defender = Hero {
+            id: 0,
+            pos: Point { x: 3500, y: 3000 },
+            shield_life: 0,
+            is_controlled: false,
+        };
+        board.player.hero_list.push(defender.clone());
+
+        // wind の届く home では待たずに、届かない所まで離れる
+        let home = Point { x: 4500, y: 3000 };
+        let dodge = dodge_point(&board, &defender, home, 100);
+        assert!(!attacker.pos.in_range(&dodge, WIND_EFFECTIVE_RADIUS));
+        assert_eq!(dodge_point(&board, &defender, home, 0), home);
+
+        // 視界の外で相手 attacker の近くにいる monster は、1回押されると引力圏に入る
+        let m = Monster {
+            id: 10,
+            pos: Point { x: 6000, y: 3300 },
+            shield_life: 0,
+            is_controlled: false,
+            health: 20,
+            v: Point { x: 400, y: 0 },
+            threat_state: MonsterThreatState::NotThreat,
+        };
+        board.monster_list.push(m);
+        let threat_list = wind_threat_list(&board, &Belief::default(), 100);
+        assert_eq!(threat_list.len(), 1);
+        assert_eq!(threat_list[0].caster, 1);
+        assert!(board
+            .player
+            .base
+            .in_range(&threat_list[0].landing, BASE_ATTRACTION_RADIUS));
+        assert!(wind_threat_list(&board, &Belief::default(), 0).is_empty());
+    }
+}
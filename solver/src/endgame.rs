@@ -0,0 +1,218 @@
+//! sudden death (turn >= 200) の戦い方
+//!
+//! 220 ターン目が終わると base の health が多い方、同じなら自陣の外で稼いだマナ (wild mana) が多い方が勝つ
+//! turn 200 からは毎ターン、spawn 地点から両陣の視界の中のランダムな点に向けて monster が湧くが、
+//! spawn 地点から base までは 8800 以上あるので、湧いた monster が自力で base に届くのは 20 ターン以上先になる
+//! なので残りのターンで health を動かすのは、今いる monster と spell で押し込んだ monster だけで、
+//! それを数えて、守りきるか、最後に攻めるか、wild mana を稼ぐかを決める
+
+use simulator::inout::*;
+use simulator::{
+    BASE_ATTACK_RADIUS, BASE_ATTRACTION_RADIUS, CENTER, MANA_GAIN_TO_ATTACK, MAX_MONSTER_VELOCITY, SPAWN_POINT_LIST,
+};
+
+use crate::geometry::MonsterPath;
+
+pub const SUDDEN_DEATH_TURN: usize = 200;
+pub const LAST_TURN: usize = 220;
+
+// hero 1体が 1ターンに稼げる wild mana の目安 (2体くらいまとめて殴れる)
+const WILD_MANA_PER_TURN: i32 = MANA_GAIN_TO_ATTACK * 2;
+// 守りに 1体残して、残りで稼ぐ
+const FARMER_COUNT: i32 = 2;
+// wild mana の差がこれ以上動かない限り、前のターンの方針を続ける (境目で方針が行き来しないように)
+const WILD_LEAD_MARGIN: i32 = 10;
+
+/// 残りのターンの方針
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EndgamePlan {
+    /// 最後まで守りきれば勝てるが、自陣に monster が来るので全員で守る
+    Defend,
+    /// このままでは負けるので、最後に攻めて相手の health を削る
+    ///
+    /// all_in なら、自陣に届く monster が無いので守りも攻めに回す
+    Attack { all_in: bool },
+    /// 守りを 1体残して自陣の外でマナを稼ぐ
+    ///
+    /// health が並びそうな時と、勝っていて自陣に届く monster が無い時
+    Farm,
+}
+
+/// 最後までに何が起きそうか
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Forecast {
+    pub turn_left: usize,
+    /// 殴りきれずに base に入る monster の数 (自分, 相手)
+    pub damage: [i32; 2],
+    /// 自陣の引力圏の中か、最後までに入ってくる monster がいる
+    pub threatened: bool,
+}
+
+pub fn turn_left(turn: usize) -> usize {
+    LAST_TURN.saturating_sub(turn)
+}
+
+/// turn に湧いた monster が、誰にも触られずに base に届く一番早いターン
+pub fn spawn_arrival_turn(turn: usize, base: &Point) -> usize {
+    let distance = SPAWN_POINT_LIST
+        .iter()
+        .flat_map(|p| [*p, p.point_symmetry(&CENTER)])
+        .map(|p| p.distance(base))
+        .min()
+        .unwrap()
+        - BASE_ATTACK_RADIUS;
+    turn + ((distance + MAX_MONSTER_VELOCITY - 1) / MAX_MONSTER_VELOCITY) as usize
+}
+
+/// 今見えている monster が、誰にも spell をかけられずに進んだ時の最後までの被害
+///
+/// これから湧く monster が間に合うなら、自陣は脅かされているものとする
+pub fn forecast(board: &Board) -> Forecast {
+    let turn_left = turn_left(board.turn);
+    let side_list = [
+        (&board.player.base, &board.player.hero_list),
+        (&board.opponent.base, &board.opponent.hero_list),
+    ];
+    let mut damage = [0, 0];
+    let mut threatened = spawn_arrival_turn(board.turn, &board.player.base) <= LAST_TURN;
+    for m in board.monster_list.iter() {
+        for (i, (base, hero_list)) in side_list.iter().enumerate() {
+            let path = MonsterPath::toward(board, m, base);
+            let arrival = match path.arrival {
+                Some(arrival) if arrival <= turn_left => arrival,
+                _ => continue,
+            };
+            if i == 0 {
+                threatened = true;
+            }
+            // 守る側の hero 全員で追いかけても、入るまでに倒しきれない
+            let hero_list = hero_list.iter().map(|h| h.pos).collect::<Vec<_>>();
+            if path.kill_turn(m.health, &hero_list).is_none_or(|turn| turn > arrival) {
+                damage[i] += 1;
+            }
+        }
+        threatened |= board.player.base.in_range(&m.pos, BASE_ATTRACTION_RADIUS);
+    }
+    Forecast {
+        turn_left,
+        damage,
+        threatened,
+    }
+}
+
+/// 最後の health の見込みと wild mana の差から方針を決める
+pub fn plan(board: &Board, forecast: &Forecast, wild_lead: i32) -> EndgamePlan {
+    let health = board.player.health - forecast.damage[0];
+    let opponent_health = board.opponent.health - forecast.damage[1];
+    // 守る必要が無ければ、万一 health が並んだ時のために稼いでおく
+    let defend = if forecast.threatened {
+        EndgamePlan::Defend
+    } else {
+        EndgamePlan::Farm
+    };
+    let attack = EndgamePlan::Attack {
+        all_in: !forecast.threatened,
+    };
+    if health > opponent_health {
+        return defend;
+    }
+    if health < opponent_health {
+        return attack;
+    }
+
+    // health が並ぶなら wild mana の勝負
+    let reachable = forecast.turn_left as i32 * WILD_MANA_PER_TURN * FARMER_COUNT;
+    if wild_lead > reachable {
+        // 相手が残りのターンで稼げる分より多く勝っている
+        defend
+    } else if wild_lead < -reachable {
+        // 稼いでも追いつけない
+        attack
+    } else {
+        EndgamePlan::Farm
+    }
+}
+
+/// 前のターンの方針 prev を、見込みが境目から十分に動いた時だけ変える
+///
+/// wild mana の差が WILD_LEAD_MARGIN だけずれても prev のままなら、prev を続ける
+pub fn replan(prev: Option<EndgamePlan>, board: &Board, forecast: &Forecast, wild_lead: i32) -> EndgamePlan {
+    let next = plan(board, forecast, wild_lead);
+    match prev {
+        Some(prev)
+            if prev != next
+                && [wild_lead - WILD_LEAD_MARGIN, wild_lead + WILD_LEAD_MARGIN]
+                    .iter()
+                    .any(|&lead| plan(board, forecast, lead) == prev) =>
+        {
+            prev
+        }
+        _ => next,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use simulator::{MAX_X, MAX_Y};
+
+    #[test]
+    fn test_plan() {
+        // sudden death で湧いた monster は、最後までに base に届かない
+        let base = Point { x: 0, y: 0 };
+        assert!(spawn_arrival_turn(SUDDEN_DEATH_TURN, &base) > LAST_TURN);
+        assert!(spawn_arrival_turn(SUDDEN_DEATH_TURN - 30, &base) <= LAST_TURN);
+
+        let mut board = Board {
+            player: Player::new(),
+            opponent: Player::new(),
+            monster_list: vec![],
+            turn: 210,
+        };
+        board.player.health = 2;
+        board.opponent.health = 2;
+        board.opponent.base = Point { x: MAX_X, y: MAX_Y };
+        let quiet = forecast(&board);
+        assert_eq!(quiet.damage, [0, 0]);
+        assert!(!quiet.threatened);
+        assert_eq!(plan(&board, &quiet, 0), EndgamePlan::Farm);
+        assert_eq!(plan(&board, &quiet, 1000), EndgamePlan::Farm);
+        assert_eq!(plan(&board, &quiet, -1000), EndgamePlan::Attack { all_in: true });
+        // 境目の近くで wild mana の差が少し動いたくらいでは、方針を変えない
+        let reachable = quiet.turn_left as i32 * WILD_MANA_PER_TURN * FARMER_COUNT;
+        let attack = EndgamePlan::Attack { all_in: true };
+        assert_eq!(plan(&board, &quiet, -reachable - 1), attack);
+        assert_eq!(
+            replan(Some(EndgamePlan::Farm), &board, &quiet, -reachable - 1),
+            EndgamePlan::Farm
+        );
+        assert_eq!(
+            replan(
+                Some(EndgamePlan::Farm),
+                &board,
+                &quiet,
+                -reachable - WILD_LEAD_MARGIN - 1
+            ),
+            attack
+        );
+        assert_eq!(replan(None, &board, &quiet, -reachable - 1), attack);
+
+        // 相手陣の手前にいる、相手が止めきれない monster で並べる
+        board.player.health = 1;
+        board.monster_list.push(Monster {
+            id: 10,
+            pos: Point {
+                x: MAX_X - 1000,
+                y: MAX_Y - 1000,
+            },
+            shield_life: 0,
+            is_controlled: false,
+            health: 20,
+            v: Point { x: 283, y: 283 },
+            threat_state: MonsterThreatState::OpponentThreat,
+        });
+        let incoming = forecast(&board);
+        assert_eq!(incoming.damage, [0, 1]);
+        assert_eq!(plan(&board, &incoming, 0), EndgamePlan::Farm);
+    }
+}
@@ -0,0 +1,359 @@
+//! マナ集めで hero が向かう位置の最適化
+//!
+//! monster の位置を中心に攻撃範囲の円を置くと、一番多くの円に入る点は
+//! 「円の中心」か「2つの円の交点」のどれかになる (hero の移動範囲の境界も円として扱う)
+//! 候補点を全部列挙して数えるので、近似ではなく 1ターンで一番多く殴れる位置が分かる
+//!
+//! 数ターン分は、候補点の上位から beam 状に展開して探す
+//! マナ集めをしている hero 同士は、先に決めた hero が殴る分だけ monster の health を減らしてから次の hero を決める
+
+use simulator::inout::*;
+use simulator::{FPoint, BASE_ATTRACTION_RADIUS, HERO_ATTACK_RADIUS, MANA_GAIN_TO_ATTACK, MAX_HERO_VELOCITY};
+
+use crate::geometry::MonsterPath;
+
+// 何ターン先まで計画するか
+pub const PLAN_TURN: usize = 3;
+// 各ターンで展開する候補点の数
+const BEAM_WIDTH: usize = 4;
+// 自陣の base の引力圏の外で殴ると wild mana も増えるので、重く数える
+const WILD_MANA_WEIGHT: i32 = 2;
+const BASE_MANA_WEIGHT: i32 = 1;
+// 交点を整数座標に丸めても円の内側に残るように、少し小さい円で交点を求める
+const ROUNDING_MARGIN: f64 = 2.0;
+
+/// 殴る対象の位置と、殴った時の価値
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Target {
+    pub pos: Point,
+    pub weight: i32,
+}
+
+impl Target {
+    pub fn new(board: &Board, pos: Point) -> Target {
+        let weight = if board.player.base.in_range(&pos, BASE_ATTRACTION_RADIUS) {
+            BASE_MANA_WEIGHT
+        } else {
+            WILD_MANA_WEIGHT
+        };
+        Target { pos, weight }
+    }
+}
+
+/// hero が向かう位置と、そこで殴れる数
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct HitPoint {
+    pub point: Point,
+    pub hit: usize,
+    /// hit を weight で重み付けしたもの
+    pub score: i32,
+}
+
+/// from から 1ターンで行ける位置のうち、target_list を殴れるものを score の高い順に
+///
+/// 同じ score なら from に近い順 (何も殴れない位置は含まない)
+pub fn hit_point_list(from: &Point, target_list: &[Target]) -> Vec<HitPoint> {
+    let reach = (from.to_f64(), MAX_HERO_VELOCITY as f64 - ROUNDING_MARGIN);
+    let circle_list = target_list
+        .iter()
+        .map(|t| (t.pos.to_f64(), HERO_ATTACK_RADIUS as f64 - ROUNDING_MARGIN))
+        .collect::<Vec<_>>();
+
+    let mut candidate_list = vec![*from];
+    for (i, c1) in circle_list.iter().enumerate() {
+        // 円の中心 (遠ければ、そちらに向かって行けるところまで)
+        candidate_list.push(toward(from, &target_list[i].pos, reach.1));
+        for p in intersection(c1, &reach) {
+            candidate_list.push(p.to::<i32>());
+        }
+        for c2 in circle_list[i + 1..].iter() {
+            for p in intersection(c1, c2) {
+                candidate_list.push(p.to::<i32>());
+            }
+        }
+    }
+
+    let mut ret = candidate_list
+        .into_iter()
+        .filter(|p| from.in_range(p, MAX_HERO_VELOCITY))
+        .map(|point| evaluate(point, target_list))
+        .filter(|h| h.hit > 0)
+        .collect::<Vec<_>>();
+    ret.sort_by_key(|h| (std::cmp::Reverse((h.score, h.hit)), h.point.distance2(from)));
+    ret.dedup_by_key(|h| h.point);
+    ret
+}
+
+/// from から 1ターンで行けて target を必ず殴れる位置を、hit の多い順に
+///
+/// from が既に target を殴れる位置なら、空にはならない
+pub fn target_hit_point_list(board: &Board, from: &Point, target: &Monster) -> Vec<HitPoint> {
+    let target_list = board
+        .monster_list
+        .iter()
+        .map(|m| Target::new(board, m.pos))
+        .collect::<Vec<_>>();
+    hit_point_list(from, &target_list)
+        .into_iter()
+        .filter(|h| target.pos.in_range(&h.point, HERO_ATTACK_RADIUS))
+        .collect()
+}
+
+/// マナ集めの hero 1体分の計画
+#[derive(Clone, Debug, PartialEq)]
+pub struct FarmPlan {
+    pub hero_id: usize,
+    /// 今ターン向かう位置
+    pub point: Point,
+    /// 今ターンに殴れる数
+    pub hit: usize,
+    /// PLAN_TURN ターン分の score の合計
+    pub score: i32,
+    /// 今ターンの候補点 (annotation 用)
+    pub candidate_list: Vec<HitPoint>,
+}
+
+/// hero_id_list の hero が PLAN_TURN ターンで一番マナを稼げる動きを、前の hero から順に決める
+///
+/// enable が false の位置には向かわない
+pub fn plan(board: &Board, hero_id_list: &[usize], enable: impl Fn(&Point) -> bool) -> Vec<FarmPlan> {
+    let path_list = board
+        .monster_list
+        .iter()
+        .map(|m| MonsterPath::new(board, m).path)
+        .collect::<Vec<_>>();
+    // target_list[t] は t ターン後に殴る時の monster の位置
+    let target_list = (0..PLAN_TURN)
+        .map(|t| {
+            path_list
+                .iter()
+                .map(|path| Target::new(board, path[t]))
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    // health[t][i] は、先に決めた hero が殴った後の、t ターン目に殴る前の health
+    let mut health = vec![board.monster_list.iter().map(|m| m.health).collect::<Vec<_>>(); PLAN_TURN];
+
+    let mut ret = vec![];
+    for &hero_id in hero_id_list.iter() {
+        let from = board.player.hero_list[hero_id].pos;
+        let mut search = Search {
+            target_list: &target_list,
+            enable: &enable,
+            best: None,
+        };
+        search.dfs(0, &from, &mut health.clone(), &mut vec![], 0);
+        let (path, score) = search.best.unwrap();
+
+        let alive_list = alive(&target_list[0], &health[0]);
+        let candidate_list = hit_point_list(&from, &alive_list)
+            .into_iter()
+            .filter(|h| enable(&h.point))
+            .collect::<Vec<_>>();
+        let first = evaluate(path[0], &alive_list);
+
+        // 次の hero は、この hero が殴った後の health で考える
+        for (t, point) in path.iter().enumerate() {
+            for (i, target) in target_list[t].iter().enumerate() {
+                if health[t][i] > 0 && target.pos.in_range(point, HERO_ATTACK_RADIUS) {
+                    for h in health[t..].iter_mut() {
+                        h[i] -= MANA_GAIN_TO_ATTACK;
+                    }
+                }
+            }
+        }
+
+        ret.push(FarmPlan {
+            hero_id,
+            point: path[0],
+            hit: first.hit,
+            score,
+            candidate_list,
+        });
+    }
+    ret
+}
+
+struct Search<'a, F> {
+    target_list: &'a [Vec<Target>],
+    enable: &'a F,
+    best: Option<(Vec<Point>, i32)>,
+}
+
+impl<'a, F> Search<'a, F>
+where
+    F: Fn(&Point) -> bool,
+{
+    fn dfs(&mut self, turn: usize, from: &Point, health: &mut [Vec<i32>], path: &mut Vec<Point>, score: i32) {
+        if turn == PLAN_TURN {
+            if self.best.as_ref().is_none_or(|(_, best)| score > *best) {
+                self.best = Some((path.clone(), score));
+            }
+            return;
+        }
+
+        let target_list = alive(&self.target_list[turn], &health[turn]);
+        let mut candidate_list = hit_point_list(from, &target_list)
+            .into_iter()
+            .filter(|h| (self.enable)(&h.point))
+            .take(BEAM_WIDTH)
+            .collect::<Vec<_>>();
+        // 今殴れるものが少なければ、次のターンに殴れそうな monster に近づく
+        if candidate_list.len() < BEAM_WIDTH {
+            if let Some(next_list) = self.target_list.get(turn + 1) {
+                let mut approach_list = next_list
+                    .iter()
+                    .enumerate()
+                    .filter(|(i, _)| health[turn + 1][*i] > 0)
+                    .map(|(_, t)| (t.pos.distance2(from), toward(from, &t.pos, MAX_HERO_VELOCITY as f64)))
+                    .filter(|(_, p)| (self.enable)(p))
+                    .collect::<Vec<_>>();
+                approach_list.sort_by_key(|(distance2, _)| *distance2);
+                let rest = BEAM_WIDTH - candidate_list.len();
+                candidate_list.extend(
+                    approach_list
+                        .into_iter()
+                        .take(rest)
+                        .map(|(_, p)| evaluate(p, &target_list)),
+                );
+            }
+        }
+        // 行き先がなければ、その場に留まる
+        if candidate_list.is_empty() {
+            candidate_list.push(evaluate(*from, &target_list));
+        }
+
+        for h in candidate_list {
+            // この位置で殴った分、以降のターンの health を減らす
+            let hit_list = (0..self.target_list[turn].len())
+                .filter(|&i| {
+                    health[turn][i] > 0 && self.target_list[turn][i].pos.in_range(&h.point, HERO_ATTACK_RADIUS)
+                })
+                .collect::<Vec<_>>();
+            for &i in hit_list.iter() {
+                for hp in health[turn..].iter_mut() {
+                    hp[i] -= MANA_GAIN_TO_ATTACK;
+                }
+            }
+            path.push(h.point);
+            self.dfs(turn + 1, &h.point, health, path, score + h.score);
+            path.pop();
+            for &i in hit_list.iter() {
+                for hp in health[turn..].iter_mut() {
+                    hp[i] += MANA_GAIN_TO_ATTACK;
+                }
+            }
+        }
+    }
+}
+
+/// health が残っているものだけ (死んだ monster は殴れない)
+fn alive(target_list: &[Target], health: &[i32]) -> Vec<Target> {
+    target_list
+        .iter()
+        .zip(health.iter())
+        .filter(|(_, &h)| h > 0)
+        .map(|(t, _)| *t)
+        .collect()
+}
+
+fn evaluate(point: Point, target_list: &[Target]) -> HitPoint {
+    let hit_list = target_list
+        .iter()
+        .filter(|t| t.pos.in_range(&point, HERO_ATTACK_RADIUS))
+        .collect::<Vec<_>>();
+    HitPoint {
+        point,
+        hit: hit_list.len(),
+        score: hit_list.iter().map(|t| t.weight).sum(),
+    }
+}
+
+/// from から to に向かって、最大 distance 進んだ位置
+fn toward(from: &Point, to: &Point, distance: f64) -> Point {
+    let d = (*to - *from).to_f64();
+    let norm = d.norm2().sqrt();
+    if norm <= distance {
+        *to
+    } else {
+        *from + (d * (distance / norm)).to::<i32>()
+    }
+}
+
+/// 2つの円 (中心, 半径) の交点
+fn intersection(c1: &(FPoint, f64), c2: &(FPoint, f64)) -> Vec<FPoint> {
+    let ((p1, r1), (p2, r2)) = (*c1, *c2);
+    let d = p2 - p1;
+    let dist = d.norm2().sqrt();
+    if dist == 0.0 || dist > r1 + r2 || dist < (r1 - r2).abs() {
+        return vec![];
+    }
+    // p1 から交点を結ぶ線までの距離 a と、その線の半分の長さ h
+    let a = (r1 * r1 - r2 * r2 + dist * dist) / (2.0 * dist);
+    let h = (r1 * r1 - a * a).max(0.0).sqrt();
+    let e = d * (1.0 / dist);
+    let mid = p1 + e * a;
+    let normal = FPoint { x: -e.y, y: e.x };
+    vec![mid + normal * h, mid - normal * h]
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use simulator::{MAX_X, MAX_Y};
+
+    #[test]
+    fn test_hit_point_list() {
+        // 3体の monster は重心に行けば全部殴れるが、重心は 1ターンでは届かない
+        let target_list = [
+            Point { x: 10000, y: 5000 },
+            Point { x: 11400, y: 5000 },
+            Point { x: 10700, y: 6200 },
+        ]
+        .map(|pos| Target { pos, weight: 1 });
+        let from = Point { x: 10700, y: 3900 };
+        let best = hit_point_list(&from, &target_list)[0];
+        assert!(from.in_range(&best.point, MAX_HERO_VELOCITY));
+        // 2体の中心 (10700, 5000) より手前の、2体にぎりぎり当たる位置を見つける
+        assert_eq!(best.hit, 2);
+        assert!(best.point.y < 4700);
+
+        // 2体目の hero は、1体目がすぐに倒す monster 10 は諦めて monster 12 を殴りに行く
+        let mut board = Board {
+            player: Player::new(),
+            opponent: Player::new(),
+            monster_list: vec![],
+            turn: 50,
+        };
+        board.opponent.base = Point { x: MAX_X, y: MAX_Y };
+        board.monster_list = [(10, Point { x: 9000, y: 4500 }), (12, Point { x: 12000, y: 4500 })]
+            .iter()
+            .map(|&(id, pos)| Monster {
+                id,
+                pos,
+                shield_life: 0,
+                is_controlled: false,
+                health: if id == 10 { 2 } else { 10 },
+                v: Point { x: 0, y: 0 },
+                threat_state: MonsterThreatState::NotThreat,
+            })
+            .collect();
+        board.player.hero_list = [Point { x: 9000, y: 3800 }, Point { x: 9000, y: 3700 }]
+            .iter()
+            .enumerate()
+            .map(|(id, &pos)| Hero {
+                id: id as i32,
+                pos,
+                shield_life: 0,
+                is_controlled: false,
+            })
+            .collect();
+        let plan_list = plan(&board, &[0, 1], |_| true);
+        assert_eq!(plan_list.len(), 2);
+        assert_eq!(plan_list[0].hit, 1);
+        assert_eq!(plan_list[0].score, 2 * WILD_MANA_WEIGHT);
+        assert_eq!(plan_list[1].hit, 0);
+        assert_eq!(plan_list[1].score, WILD_MANA_WEIGHT);
+        assert!(plan_list[1].point.x > 9000);
+    }
+}
@@ -0,0 +1,194 @@
+//! 見えている盤面から、探索の初期状態を作る
+//!
+//! board には霧の外の monster や相手 hero が映っていないので、記憶 (Belief) と
+//! monster が点対称に湧くことを使って埋めて、simulator と同じルールで進められる GameState にする
+
+use simulator::inout::*;
+use simulator::state::{self, Command, Entity, GameState, SharedRandom, HERO_SIZE};
+use simulator::{CachedRandom, Simulator, CENTER, VISIBLE_RADIUS_FROM_BASE, VISIBLE_RADIUS_FROM_HERO};
+
+// 本物の seed は分からないので、乱数 table は solver が自分で持つ
+const RANDOM_TABLE_SIZE: usize = 65535;
+
+// hero が 0..6 を使うので、monster の id はここから2つずつ振られる
+const FIRST_MONSTER_ID: i32 = 6;
+
+/// board に映っていないものの推測
+#[derive(Clone, Debug, Default)]
+pub struct Belief {
+    /// 霧の外にいるはずの monster (最後に見た状態を進めたものなど)
+    pub monster_list: Vec<Monster>,
+    /// 霧の外にいるはずの相手 hero
+    pub opponent_hero_list: Vec<Hero>,
+}
+
+#[derive(Clone)]
+pub struct ForwardModel {
+    random: CachedRandom,
+}
+
+impl ForwardModel {
+    pub fn new(seed: u64) -> ForwardModel {
+        ForwardModel {
+            random: CachedRandom::new(RANDOM_TABLE_SIZE, seed),
+        }
+    }
+
+    /// board と belief から、ありえる盤面を1つ作る
+    ///
+    /// sample は乱数 table の開始位置で、変えると今後湧く monster の向きが変わる
+    pub fn determinize(&self, board: &Board, belief: &Belief, sample: usize) -> GameState<'_> {
+        let visible = |p: &Point| {
+            board.player.base.in_range(p, VISIBLE_RADIUS_FROM_BASE)
+                || board
+                    .player
+                    .hero_list
+                    .iter()
+                    .any(|h| h.pos.in_range(p, VISIBLE_RADIUS_FROM_HERO))
+        };
+
+        // monster: 見えているもの > belief > 見えているものの点対称な片割れ
+        let mut monster_list = board.monster_list.clone();
+        for m in belief.monster_list.iter() {
+            if monster_list.iter().all(|known| known.id != m.id) {
+                monster_list.push(m.clone());
+            }
+        }
+        for i in 0..monster_list.len() {
+            let m = &monster_list[i];
+            let twin_id = m.id ^ 1;
+            let pos = m.pos.point_symmetry(&CENTER);
+            if monster_list.iter().any(|known| known.id == twin_id)
+                || visible(&pos)
+                || !Simulator::is_in_gameboard(&pos)
+            {
+                continue;
+            }
+            monster_list.push(Monster {
+                id: twin_id,
+                pos,
+                v: m.v.flip(),
+                ..m.clone()
+            });
+        }
+
+        let opponent_hero_list = self.opponent_hero_list(board, belief);
+        let player = |p: &simulator::inout::Player, hero_list: &[Hero]| state::Player {
+            health: p.health,
+            mana: p.mana,
+            // 見えないので、wild mana は無いものとする
+            wild_mana: 0,
+            base: p.base,
+            hero_list: [0, 1, 2].map(|hero_id| entity(&hero_list[hero_id])),
+        };
+
+        let next_id = monster_list
+            .iter()
+            .map(|m| (m.id / 2 + 1) * 2)
+            .max()
+            .unwrap_or(FIRST_MONSTER_ID)
+            .max(FIRST_MONSTER_ID);
+        let mut ret = GameState::new(
+            [
+                player(&board.player, &board.player.hero_list),
+                player(&board.opponent, &opponent_hero_list),
+            ],
+            board.turn,
+            next_id,
+            SharedRandom::new(&self.random, sample),
+        );
+        for m in monster_list.iter() {
+            ret.push_monster(state::Monster {
+                entity: Entity::new(m.id, m.pos, m.v, m.shield_life),
+                health: m.health,
+            });
+        }
+        ret
+    }
+
+    /// 相手 hero を id 順に3体揃える
+    ///
+    /// 見えていなくて belief にも無い hero は、自分の同じ番号の hero の点対称な位置にいるとする
+    fn opponent_hero_list(&self, board: &Board, belief: &Belief) -> Vec<Hero> {
+        let first_id = if board.player.hero_list[0].id == 0 {
+            HERO_SIZE as i32
+        } else {
+            0
+        };
+        (0..HERO_SIZE)
+            .map(|hero_id| {
+                let id = first_id + hero_id as i32;
+                board
+                    .opponent
+                    .hero_list
+                    .iter()
+                    .chain(belief.opponent_hero_list.iter())
+                    .find(|h| h.id == id)
+                    .cloned()
+                    .unwrap_or_else(|| Hero {
+                        id,
+                        pos: board.player.hero_list[hero_id].pos.point_symmetry(&CENTER),
+                        shield_life: 0,
+                        is_controlled: false,
+                    })
+            })
+            .collect()
+    }
+}
+
+/// solver の出した action を、GameState に渡せる形にする
+pub fn to_command_list(action_list: &[Action]) -> [Command; HERO_SIZE] {
+    let mut ret = [Command::Wait; HERO_SIZE];
+    for (command, action) in ret.iter_mut().zip(action_list.iter()) {
+        *command = Command::from(action);
+    }
+    ret
+}
+
+fn entity(hero: &Hero) -> Entity {
+    // hero の速度は次の move で決まるので、0 で良い
+    Entity::new(hero.id, hero.pos, Point { x: 0, y: 0 }, hero.shield_life)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_determinize() {
+        let mut sim = Simulator::new(1);
+        for _ in 0..30 {
+            let wait = || (0..3).map(|_| Action::Wait { message: String::new() }).collect();
+            sim.next_state(wait(), wait());
+        }
+        let board = sim.to_board(0);
+        let model = ForwardModel::new(0);
+        let state = model.determinize(&board, &Belief::default(), 0);
+
+        // 見えているものはそのまま
+        for m in board.monster_list.iter() {
+            assert!(state
+                .monster_list()
+                .iter()
+                .any(|s| s.entity.id == m.id && s.entity.position == m.pos));
+        }
+        assert_eq!(state.player_list[1].hero_list.map(|h| h.id), [3, 4, 5]);
+
+        // 片割れから補った monster は、本当の位置の近くにいる
+        let filled = state
+            .monster_list()
+            .iter()
+            .filter(|s| board.monster_list.iter().all(|m| m.id != s.entity.id))
+            .collect::<Vec<_>>();
+        assert!(!filled.is_empty());
+        for s in filled {
+            let m = sim
+                .components
+                .monster_list
+                .iter()
+                .find(|m| m.component.id == s.entity.id)
+                .unwrap();
+            assert!(m.component.position.distance(&s.entity.position) <= 2);
+        }
+    }
+}
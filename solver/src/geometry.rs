@@ -0,0 +1,142 @@
+//! monster に追いつく位置と、倒しきれるかの計算
+//!
+//! monster は誰にも触られなければ直進し、base の近くに入ると base に向かう
+//! hero は 1ターンに 800 動いてから、800 以内の monster を殴る (monster が動く前に殴る)
+
+use simulator::inout::*;
+use simulator::{
+    BASE_ATTACK_RADIUS, BASE_ATTRACTION_RADIUS, HERO_ATTACK_RADIUS, MANA_GAIN_TO_ATTACK, MAX_HERO_VELOCITY,
+    MAX_MONSTER_VELOCITY,
+};
+
+// 何ターン先まで見るか
+pub const HORIZON: usize = 20;
+
+/// hero が monster を殴り始められるターンと、その時の monster の位置
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Interception {
+    /// 1始まり (1 なら今ターンの移動で殴れる)
+    pub turn: usize,
+    /// ここに向かって動けば、turn ターン目に殴れる
+    pub point: Point,
+}
+
+/// monster の HORIZON ターン先までの位置
+#[derive(Clone, Debug)]
+pub struct MonsterPath {
+    /// path[t] は今から t ターン後の位置
+    pub path: Vec<Point>,
+    /// 自陣の base に入ってダメージを与えるターン
+    pub arrival: Option<usize>,
+}
+
+impl MonsterPath {
+    pub fn new(board: &Board, m: &Monster) -> MonsterPath {
+        Self::toward(board, m, &board.player.base)
+    }
+
+    /// arrival を base (相手陣でも良い) に入るターンとする
+    pub fn toward(board: &Board, m: &Monster, base: &Point) -> MonsterPath {
+        let mut path = vec![m.pos];
+        let mut pos = m.pos;
+        let mut v = m.v;
+        for _ in 0..HORIZON {
+            pos = pos + v;
+            if let Some(base) = [board.player.base, board.opponent.base]
+                .iter()
+                .find(|base| base.in_range(&pos, BASE_ATTRACTION_RADIUS))
+            {
+                v = ((*base - pos).to_f64().normalize() * MAX_MONSTER_VELOCITY as f64).to::<i32>();
+            }
+            path.push(pos);
+        }
+        let arrival = path.iter().position(|p| base.in_range(p, BASE_ATTACK_RADIUS));
+        MonsterPath { path, arrival }
+    }
+
+    /// 殴れるのは base に入るまで (入らないなら HORIZON まで)
+    fn last_turn(&self) -> usize {
+        self.arrival.unwrap_or(HORIZON)
+    }
+
+    /// hero が一番早く殴り始められるターンと位置
+    pub fn intercept(&self, hero: &Point) -> Option<Interception> {
+        // k ターン目には、hero は移動してから k-1 ターン後の位置にいる monster を殴る
+        (1..=self.last_turn())
+            .map(|turn| Interception {
+                turn,
+                point: self.path[turn - 1],
+            })
+            .find(|i| hero.distance(&i.point) <= MAX_HERO_VELOCITY * i.turn as i32 + HERO_ATTACK_RADIUS)
+    }
+
+    /// hero_list が全員で追いかけた時に、health を削りきるターン
+    ///
+    /// base に入るまで (入らないなら HORIZON まで) に倒せなければ None
+    pub fn kill_turn(&self, health: i32, hero_list: &[Point]) -> Option<usize> {
+        let start_list = hero_list
+            .iter()
+            .filter_map(|hero| self.intercept(hero))
+            .map(|i| i.turn)
+            .collect::<Vec<_>>();
+        let mut damage = 0;
+        (1..=self.last_turn()).find(|turn| {
+            // 一度追いついた hero は、monster より速いので殴り続けられる
+            damage += MANA_GAIN_TO_ATTACK * start_list.iter().filter(|start| *start <= turn).count() as i32;
+            damage >= health
+        })
+    }
+}
+
+/// hero が monster に一番早く追いつくために向かう位置
+///
+/// HORIZON 内に追いつけない時は、monster の次の位置に向かう
+pub fn intercept_point(board: &Board, m: &Monster, hero: &Point) -> Point {
+    MonsterPath::new(board, m)
+        .intercept(hero)
+        .map(|i| i.point)
+        .unwrap_or_else(|| m.next_pos())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use simulator::{MAX_X, MAX_Y};
+
+    #[test]
+    fn test_kill_turn() {
+        let mut board = Board {
+            player: Player::new(),
+            opponent: Player::new(),
+            monster_list: vec![],
+            turn: 50,
+        };
+        board.opponent.base = Point { x: MAX_X, y: MAX_Y };
+        // base に向かって真っ直ぐ進む monster (base の 300 以内に入るのは 10 ターン後)
+        let m = Monster {
+            id: 10,
+            pos: Point { x: 4300, y: 0 },
+            shield_life: 0,
+            is_controlled: false,
+            health: 20,
+            v: Point { x: -400, y: 0 },
+            threat_state: MonsterThreatState::PlayerThreat,
+        };
+        let path = MonsterPath::new(&board, &m);
+        assert_eq!(path.arrival, Some(10));
+
+        // 2ターン目に、1ターン後の位置 (3900, 0) で追いつく
+        let hero = Point { x: 3900, y: 2400 };
+        assert_eq!(
+            path.intercept(&hero),
+            Some(Interception {
+                turn: 2,
+                point: Point { x: 3900, y: 0 }
+            })
+        );
+
+        // 1体だと 9回しか殴れず間に合わないが、2体なら間に合う
+        assert_eq!(path.kill_turn(m.health, &[hero]), None);
+        assert_eq!(path.kill_turn(m.health, &[hero, Point { x: 4300, y: 500 }]), Some(6));
+    }
+}
@@ -1,24 +1,63 @@
-use std::{collections::HashSet, time::Instant};
+use std::collections::HashSet;
 
 use simulator::inout::*;
 use simulator::Action;
 
+use assignment::{Assignment, Defender};
+use beam::BeamSearch;
+use counter::WindThreat;
+use endgame::EndgamePlan;
+use farming::{FarmPlan, HitPoint};
+use mana::ManaEstimator;
+use mcts::Mcts;
+use opponent::{OpponentClassifier, PolicyParams};
+use planner::{ManaPlanner, Urgency};
+use redirect::Redirect;
+use role::{Formation, Role, RoleManager, Slot};
+use timer::{Phase, TimeManager, TurnTimer};
+use tracker::Tracker;
+use wind_chain::WindPlan;
+
+mod annotation;
+pub mod assignment;
+pub mod beam;
+pub mod counter;
+pub use annotation::Annotation;
+pub mod endgame;
+pub mod farming;
+pub mod forward;
+pub mod geometry;
+pub mod mana;
+pub mod mcts;
+pub mod opponent;
+pub mod params;
+pub use params::Params;
+mod planner;
+pub mod redirect;
+pub mod role;
+pub mod shield;
+pub mod timer;
+pub mod tracker;
+pub mod wind_chain;
+
 #[derive(PartialEq, Copy, Clone)]
 struct CollectManaInfo {
     home: Point,
+    // 0, 1 はマナ集め、2 は自陣で守る
+    slot: usize,
 }
 
 impl CollectManaInfo {
-    fn calculate_home_to_collect_mana(base_pos: &Point, hero_id: usize) -> Point {
+    fn calculate_home_to_collect_mana(base_pos: &Point, hero_id: usize, params: &Params) -> Point {
         if hero_id == 0 {
             Point {
-                x: MAX_X * 6 / 10,
-                y: MAX_Y * 6 / 10,
+                x: MAX_X * params.farmer0_home_percent / 100,
+                y: MAX_Y * params.farmer0_home_percent / 100,
             }
         } else if hero_id == 1 {
             Point {
-                x: MAX_X * 4 / 10,
-                y: MAX_Y * 4 / 10,
+                x: MAX_X * params.farmer1_home_percent / 100,
+                y: MAX_Y * params.farmer1_home_percent / 100,
             }
         } else {
             let rad = std::f64::consts::PI / 4.0;
@@ -29,15 +68,21 @@ impl CollectManaInfo {
         }
     }
 
-    fn new(base_pos: &Point, hero_id: usize) -> CollectManaInfo {
+    fn new(base_pos: &Point, slot: usize, params: &Params) -> CollectManaInfo {
         CollectManaInfo {
-            home: Self::calculate_home_to_collect_mana(base_pos, hero_id),
+            home: Self::calculate_home_to_collect_mana(base_pos, slot, params),
+            slot,
         }
     }
 
+    /// マナを集めずに自陣で守る
+    fn guards_base(&self) -> bool {
+        self.slot == 2
+    }
+
     fn action(&mut self, board: &Board, hero_id: usize, solver: &mut SolverState) -> Action {
         let hero = &board.player.hero_list[hero_id];
-        if hero_id == 2 {
+        if self.guards_base() {
             // 相手 hero, monster, 自分 hero 全員 WIND 圏内にいる場合は、wind!
             if solver.can_spell(board, true) {
                 for op_hero in board.opponent.hero_list.iter() {
@@ -48,7 +93,6 @@ impl CollectManaInfo {
                         {
                             // FIXME: 相手が1手で WIND を使える条件をもっと正確に書く
                             let point = hero.pos * 2 - board.player.base;
-                            solver.spell_count += 1;
                             return Action::Wind {
                                 point,
                                 message: format!("[m1]im wind"),
@@ -58,56 +102,56 @@ impl CollectManaInfo {
                 }
             }
 
-            let candidate = board
-                .monster_list
-                .iter()
-                .filter(|m| m.pos.distance(&board.player.base) <= DETECT_BASE_RADIUS)
-                .min_by_key(|m| m.pos.distance(&board.player.base));
+            let candidate = solver.assignment.target(hero_id).and_then(|id| board.monster(id));
 
             if let Some(monster) = candidate {
-                let around_alive = board
-                    .monster_list
-                    .iter()
-                    .filter(|m| m.pos.distance(&hero.pos) <= WIND_RADIUS && m.health > 2)
-                    .count()
-                    > 0;
-                // 既に monster に追いついていて、1撃じゃ死なない monster に goal されそうなら、 WIND!
-                if around_alive
-                    && monster.pos.distance(&board.player.base) <= THREASHOLD_BASE_DAMAGE_RADIUS + MAX_MONSTER_VELOCITY
-                    && monster.pos.distance(&hero.pos) <= WIND_RADIUS
-                    && solver.can_spell(board, true)
-                {
+                // 割り当てで wind を任された monster が goal されそうなら、WIND!
+                if solver.wind_assigned(board, hero_id) && solver.can_spell(board, true) {
                     let point = hero.pos * 2 - board.player.base;
-                    solver.spell_count += 1;
                     Action::Wind {
                         point,
                         message: format!("[m1]wind"),
                     }
-                } else if hero.shield_life == 0 && solver.can_spell(board, true) && solver.is_opponent_speller {
-                    solver.spell_count += 1;
+                } else if solver.needs_self_shield(board, hero) && solver.can_spell(board, true) {
                     return Action::Shield {
                         entity_id: hero.id,
                         message: format!("[m1]shield self!"),
                     };
                 } else if hero.pos.distance(&monster.pos) <= ATTACK_HIT_RADIUS {
                     // 攻撃が当たるなら、マナの収集効率が良い場所を見つける
-                    let candidate = self.enumerate_multiple_hit_with_target(board, hero_id, monster);
+                    let candidate = farming::target_hit_point_list(board, &hero.pos, monster);
                     assert!(!candidate.is_empty());
+                    solver.annotate_candidate(hero_id, &candidate);
 
                     Action::Move {
-                        point: candidate[0].1,
-                        message: format!("[m1]{}attack", candidate[0].0),
+                        point: candidate[0].point,
+                        message: format!("[m1]{}attack", candidate[0].hit),
                     }
                 } else {
-                    let (turn, point) = self.shortest_move(&monster, &hero.pos);
+                    let point = solver
+                        .assignment
+                        .intercept(hero_id)
+                        .unwrap_or_else(|| geometry::intercept_point(board, monster, &hero.pos));
                     Action::Move {
                         point,
                         message: format!("[m1]shortest"),
                     }
                 }
+            } else if let Some((monster, point)) = solver.incoming_monster(hero_id) {
+                // 見えていないけど自陣に向かってくる monster を待ち構える
+                Action::Move {
+                    point,
+                    message: format!("[m1]wait m{}", monster),
+                }
+            } else if let Some(threat) = solver.cover(hero_id) {
+                // 視界の外から wind で押し込まれそうな monster を待ち構える
+                Action::Move {
+                    point: solver.dodge(board, hero, threat.landing),
+                    message: format!("[m1]cover m{}", threat.monster_id),
+                }
             } else {
                 Action::Move {
-                    point: self.home,
+                    point: solver.dodge(board, hero, self.home),
                     message: format!("[m1]go home {:?}", self.home), // FIXME: remove home
                 }
             }
@@ -115,142 +159,42 @@ impl CollectManaInfo {
             // hero_id = 0
             // マナを集める
 
-            let point = self.home;
-            let candidate = self.enumerate_multiple_hit(board, hero_id);
-
-            if hero.shield_life == 0 && solver.can_spell(board, false) && solver.is_opponent_speller {
-                solver.spell_count += 1;
-                return Action::Shield {
-                    entity_id: hero.id,
-                    message: format!("[m2]shield self!"),
-                };
-            } else if candidate.is_empty() {
-                // 候補がなければ自分の home に向かう
-                Action::Move {
+            // 防衛役だけでは base を守りきれない時は、手伝いに行く
+            if let Some(point) = solver.assignment.intercept(hero_id) {
+                return Action::Move {
                     point,
-                    message: format!("[m2]go home"),
-                }
-            } else {
-                // FIXME: 探索して、数手分で最もマナが稼げる所に移る
-
-                // 即時効果が発揮できる場所なら、効果が高い順に移動する
-                for (hit, pos) in candidate.iter() {
-                    if hero.pos.distance(&pos) <= MAX_PLAYER_VELOCITY {
-                        return Action::Move {
-                            point: *pos,
-                            message: format!("[m2]{}attack", hit),
-                        };
-                    }
-                }
-
-                // hit がたくさんある、一番近いところにとりあえず移っておく
-                let target = candidate
-                    .iter()
-                    .filter(|p| p.0 == candidate[0].0)
-                    .map(|(_, p)| p)
-                    .min_by_key(|p| p.distance2(&hero.pos))
-                    .unwrap();
-
-                Action::Move {
-                    point: *target,
-                    message: format!("[m2]move only"),
-                }
-            }
-        }
-    }
-
-    fn enumerate_multiple_hit(&self, board: &Board, hero_id: usize) -> Vec<(usize, Point)> {
-        let mut ret = vec![];
-
-        let enable = |p: &Point| board.player.base.distance(p) > DETECT_BASE_RADIUS + 3000;
-
-        // 3点 hit
-        for m1 in board.monster_list.iter() {
-            for m2 in board.monster_list.iter().filter(|m| m.id != m1.id) {
-                for m3 in board.monster_list.iter().filter(|m| m.id != m1.id && m.id != m2.id) {
-                    if m1.pos.distance(&m2.pos) <= 3 * ATTACK_HIT_RADIUS / 2
-                        && m1.pos.distance(&m3.pos) <= 3 * ATTACK_HIT_RADIUS / 2
-                        && m2.pos.distance(&m3.pos) <= 3 * ATTACK_HIT_RADIUS / 2
-                    {
-                        // m1, m2, m3 間の各距離が全部 3/2R 以内なら、3点の重心に行くと3体に当たる
-                        let middle = (m1.pos + m2.pos + m3.pos) / 3;
-                        if enable(&middle) {
-                            ret.push((3, middle));
-                        }
-                    }
-                }
-            }
-        }
-
-        // 2点 hit
-        for m1 in board.monster_list.iter() {
-            for m2 in board.monster_list.iter().filter(|m| m.id != m1.id) {
-                if m1.pos.distance(&m2.pos) <= 2 * ATTACK_HIT_RADIUS {
-                    let middle = (m1.pos + m2.pos) / 2;
-                    if enable(&middle) {
-                        ret.push((2, middle));
-                    }
-                }
+                    message: format!("[m1]help"),
+                };
             }
-        }
 
-        // 1点 hit
-        for m1 in board.monster_list.iter() {
-            if enable(&m1.pos) {
-                ret.push((1, m1.pos));
+            let plan = solver.farm_plan(hero_id).cloned();
+            if let Some(plan) = plan.as_ref() {
+                solver.annotate_candidate(hero_id, &plan.candidate_list);
             }
-        }
-        ret
-    }
 
-    fn shortest_move(&self, m: &Monster, pos: &Point) -> (i32, Point) {
-        // FIXME: 共通化
-        (1, m.next_pos())
-    }
-
-    /// 攻撃したい target は含んだ状態で、可能な限り hit が多い位置を探索する
-    fn enumerate_multiple_hit_with_target(
-        &self,
-        board: &Board,
-        hero_id: usize,
-        target: &Monster,
-    ) -> Vec<(usize, Point)> {
-        let mut ret = vec![];
-
-        // 3点 hit
-        for m1 in board.monster_list.iter() {
-            for m2 in board.monster_list.iter().filter(|m| m.id != m1.id) {
-                for m3 in board.monster_list.iter().filter(|m| m.id != m1.id && m.id != m2.id) {
-                    if m1.pos.distance(&m2.next_pos()) <= 3 * ATTACK_HIT_RADIUS / 2
-                        && m1.pos.distance(&m3.next_pos()) <= 3 * ATTACK_HIT_RADIUS / 2
-                        && m2.pos.distance(&m3.next_pos()) <= 3 * ATTACK_HIT_RADIUS / 2
-                        && (m1.id == target.id || m2.id == target.id || m3.id == target.id)
-                    {
-                        // m1, m2, m3 間の各距離が全部 3/2R 以内なら、3点の重心に行くと3体に当たる
-                        let middle = (m1.next_pos() + m2.next_pos() + m3.next_pos()) / 3;
-                        ret.push((3, middle));
-                    }
-                }
-            }
-        }
-
-        // 2点 hit
-        for m1 in board.monster_list.iter() {
-            for m2 in board.monster_list.iter().filter(|m| m.id != m1.id) {
-                if m1.pos.distance(&m2.pos) <= 2 * ATTACK_HIT_RADIUS && (m1.id == target.id || m2.id == target.id) {
-                    let middle = (m1.pos + m2.pos) / 2;
-                    ret.push((2, middle));
-                }
+            if solver.needs_self_shield(board, hero) && solver.can_spell(board, false) {
+                return Action::Shield {
+                    entity_id: hero.id,
+                    message: format!("[m2]shield self!"),
+                };
             }
-        }
-
-        // 1点 hit
-        for m1 in board.monster_list.iter() {
-            if m1.id == target.id {
-                ret.push((1, m1.pos));
+            match plan {
+                // 数ターン分で一番マナが稼げる動きに従う
+                Some(plan) if plan.score > 0 => Action::Move {
+                    point: plan.point,
+                    message: if plan.hit > 0 {
+                        format!("[m2]{}attack", plan.hit)
+                    } else {
+                        format!("[m2]move only")
+                    },
+                },
+                // 候補がなければ自分の home に向かう (相手の wind の届かない所で待つ)
+                _ => Action::Move {
+                    point: solver.dodge(board, hero, self.home),
+                    message: format!("[m2]go home"),
+                },
             }
         }
-        ret
     }
 }
 
@@ -262,17 +206,31 @@ struct AttackerInfo {
 }
 
 impl AttackerInfo {
-    fn new() -> AttackerInfo {
+    fn new(params: &Params) -> AttackerInfo {
         AttackerInfo {
             home: Point {
-                x: MAX_X - 2000,
-                y: MAX_Y - 2000,
+                x: MAX_X - params.attacker_home_offset,
+                y: MAX_Y - params.attacker_home_offset,
             },
             idle_counter: 0,
             home_shifted: false,
         }
     }
 
+    /// wind で押し込む計画に入っていれば、それに従う (wind のマナが貰えなければ従わない)
+    fn follow_wind_plan(board: &Board, hero_id: usize, solver: &mut SolverState) -> Option<Action> {
+        let action = solver.wind_plan.as_ref()?.action(hero_id)?;
+        match action {
+            Action::Wind { .. } => {
+                if !solver.can_spell(board, false) {
+                    return None;
+                }
+                Some(action)
+            }
+            _ => Some(action),
+        }
+    }
+
     fn action(&mut self, board: &Board, hero_id: usize, solver: &mut SolverState) -> Action {
         let hero = &board.player.hero_list[hero_id];
 
@@ -280,22 +238,23 @@ impl AttackerInfo {
         if !self.home_shifted && self.home == hero.pos {
             self.idle_counter += 1;
 
-            if self.idle_counter == 10 {
+            if self.idle_counter == solver.params.attacker_idle_turn as usize {
                 self.home_shifted = true;
-                self.home.x -= 1000;
-                self.home.y -= 1000;
+                self.home.x -= solver.params.attacker_home_shift;
+                self.home.y -= solver.params.attacker_home_shift;
             }
         } else {
             self.idle_counter = 0;
         }
 
-        if hero.shield_life == 0 && solver.can_spell(board, false) && solver.is_opponent_speller {
-            solver.spell_count += 1;
+        if solver.needs_self_shield(board, hero) && solver.can_spell(board, false) {
             Action::Shield {
                 entity_id: hero.id,
                 message: format!("[at]shield self!"),
             }
-        } else if self.home.distance(&hero.pos) > 4000 {
+        } else if let Some(action) = Self::follow_wind_plan(board, hero_id, solver) {
+            action
+        } else if self.home.distance(&hero.pos) > solver.params.front_return_distance {
             Action::Move {
                 point: self.home,
                 message: format!("[at]home 1"),
@@ -334,7 +293,6 @@ impl AttackerInfo {
                 .min_by_key(|op_h| op_h.pos.distance(&board.opponent.base))
                 .unwrap();
             if op_hero.pos.distance(&hero.pos) <= CONTROL_RADIUS && solver.can_spell(board, false) {
-                solver.spell_count += 1;
                 Action::Control {
                     entity_id: op_hero.id,
                     point: op_hero.pos * 2 - board.opponent.base,
@@ -367,29 +325,29 @@ impl AttackerInfo {
             {
                 if hero.pos.distance(&monster.pos) <= WIND_RADIUS && solver.can_spell(board, false) {
                     // wind の方が到達速度が速そう
-                    solver.spell_count += 1;
                     Action::Wind {
                         point: board.opponent.base,
                         message: format!("[at]shield"),
                     }
                 } else {
-                    let (_, point) = self.shortest_move(&monster, &hero.pos);
+                    let point = geometry::intercept_point(board, monster, &hero.pos);
                     Action::Move {
                         point,
                         message: format!("[at]shortest"),
                     }
                 }
             } else {
-                // 守備がいるなら、shield を張りたい
-                if hero.pos.distance(&monster.pos) <= SHIELD_RADIUS && solver.can_spell(board, false) {
-                    // そうでなければ shield で包む
-                    solver.spell_count += 1;
+                // 守備がいるなら、base に届くまで守りきれる時に shield を張りたい
+                if hero.pos.distance(&monster.pos) <= SHIELD_RADIUS
+                    && shield::monster_needs_shield(board, monster, solver.mana.estimate())
+                    && solver.can_spell(board, false)
+                {
                     Action::Shield {
                         entity_id: monster.id,
                         message: format!("[at]shield"),
                     }
                 } else {
-                    let (_, point) = self.shortest_move(&monster, &hero.pos);
+                    let point = geometry::intercept_point(board, monster, &hero.pos);
                     Action::Move {
                         point,
                         message: format!("[at]shortest"),
@@ -404,11 +362,6 @@ impl AttackerInfo {
             }
         }
     }
-
-    fn shortest_move(&self, m: &Monster, pos: &Point) -> (i32, Point) {
-        // FIXME: 共通化
-        (1, m.next_pos())
-    }
 }
 
 #[derive(PartialEq, Clone)]
@@ -418,11 +371,11 @@ struct MidFielderInfo {
 }
 
 impl MidFielderInfo {
-    fn new() -> MidFielderInfo {
+    fn new(params: &Params) -> MidFielderInfo {
         MidFielderInfo {
             home: Point {
-                y: MAX_Y - 5500,
-                x: MAX_X - 5500,
+                y: MAX_Y - params.midfielder_home_offset,
+                x: MAX_X - params.midfielder_home_offset,
             },
             assisted: HashSet::new(),
         }
@@ -431,14 +384,13 @@ impl MidFielderInfo {
     fn action(&mut self, board: &Board, hero_id: usize, solver: &mut SolverState) -> Action {
         let hero = &board.player.hero_list[hero_id];
 
-        if hero.shield_life == 0 && solver.can_spell(board, false) && solver.is_opponent_speller {
-            // 敵が邪魔をしてくるやつで、シールドが切れたら張り直す
-            solver.spell_count += 1;
+        if solver.needs_self_shield(board, hero) && solver.can_spell(board, false) {
+            // 敵が邪魔をしてくるやつで、近くにいる間はシールドが切れたら張り直す
             Action::Shield {
                 entity_id: hero.id,
                 message: format!("[as]shield self!"),
             }
-        } else if self.home.distance(&hero.pos) > 4000 {
+        } else if self.home.distance(&hero.pos) > solver.params.front_return_distance {
             // 前線に十分近くなければ、前線へ移動を優先
             Action::Move {
                 point: self.home,
@@ -463,36 +415,22 @@ impl MidFielderInfo {
             })
             .min_by_key(|m| hero.pos.distance(&m.pos))
         {
-            // CONTROL 中に wind されると、方向を変えないので control が無駄になってしまう
-
-            if hero.pos.distance(&target.pos) < CONTROL_RADIUS && solver.can_spell(board, false) {
-                solver.spell_count += 1;
-
-                let edge_list = [
-                    Point {
-                        x: MAX_X + MAX_MONSTER_VELOCITY - DETECT_BASE_RADIUS,
-                        y: MAX_Y - MAX_MONSTER_VELOCITY,
-                    },
-                    Point {
-                        x: MAX_X - MAX_MONSTER_VELOCITY,
-                        y: MAX_Y + MAX_MONSTER_VELOCITY - DETECT_BASE_RADIUS,
-                    },
-                ];
-
-                let goal = edge_list.iter().min_by_key(|p| p.distance(&target.pos)).unwrap();
-                // control で送る
-                solver.midfielder_countrol_count += 1;
-                Action::Control {
-                    entity_id: target.id,
-                    point: *goal,
-                    message: format!("[as]control"),
-                }
-            } else {
-                // 近い monster に近づく
-                let (turn, point) = self.shortest_move(&target, &hero.pos);
-                Action::Move {
-                    point,
-                    message: format!("[as]shortest"),
+            // 相手陣に一番早く入る向きに control で送る (wind の方が早い時や、control が wind で無駄になりそうな時は wind)
+            match redirect::plan(board, target, &hero.pos) {
+                Some(plan) if solver.can_spell(board, false) => match plan.spell {
+                    Redirect::Control { .. } => {
+                        solver.midfielder_countrol_count += 1;
+                        plan.action("[as]control".to_string())
+                    }
+                    Redirect::Wind { .. } => plan.action("[as]wind".to_string()),
+                },
+                _ => {
+                    // 近い monster に近づく
+                    let point = geometry::intercept_point(board, target, &hero.pos);
+                    Action::Move {
+                        point,
+                        message: format!("[as]shortest"),
+                    }
                 }
             }
         } else {
@@ -502,129 +440,99 @@ impl MidFielderInfo {
             }
         }
     }
-
-    fn shortest_move(&self, m: &Monster, pos: &Point) -> (i32, Point) {
-        (1, m.next_pos())
-    }
 }
 
+// 守りが複数いる時の待機位置の、自陣から見た向き [度] (0番目は対角線上)
+const DEFENDER_HOME_ANGLE_LIST: [f64; 3] = [45.0, 70.0, 20.0];
+
 #[derive(PartialEq, Copy, Clone)]
 struct DefenderInfo {
     home: Point,
+    // 守りの中で何番目か
+    index: usize,
 }
 
 impl DefenderInfo {
-    fn new() -> DefenderInfo {
+    fn new(policy: &PolicyParams, index: usize) -> DefenderInfo {
         DefenderInfo {
-            home: Point { y: 3000, x: 3000 },
+            home: Self::home(policy, index),
+            index,
+        }
+    }
+
+    /// 自陣から defender_distance 離れた待機位置
+    ///
+    /// 複数で守る時は、同じ所に固まらないように向きをずらす
+    fn home(policy: &PolicyParams, index: usize) -> Point {
+        let angle = DEFENDER_HOME_ANGLE_LIST[index % DEFENDER_HOME_ANGLE_LIST.len()].to_radians();
+        let distance = policy.defender_distance as f64;
+        Point {
+            x: (distance * angle.cos()).round() as i32,
+            y: (distance * angle.sin()).round() as i32,
         }
     }
+
     fn action(&mut self, board: &Board, hero_id: usize, solver: &mut SolverState) -> Action {
         let hero = &board.player.hero_list[hero_id];
 
-        // base に一番近いやつを殴り続ける
-        let candidate = board
-            .monster_list
-            .iter()
-            .filter(|m| m.pos.distance(&board.player.base) <= DETECT_BASE_RADIUS)
-            .min_by_key(|m| m.pos.distance(&board.player.base));
+        // 相手の戦略に合わせて、待機位置を base から離したり近づけたりする
+        self.home = Self::home(&solver.opponent.params(), self.index);
+
+        // 割り当てられた monster を殴り続ける
+        let candidate = solver.assignment.target(hero_id).and_then(|id| board.monster(id));
 
         if let Some(monster) = candidate {
-            let around_alive = board
-                .monster_list
-                .iter()
-                .filter(|m| m.pos.distance(&hero.pos) <= WIND_RADIUS && m.health > 2)
-                .count()
-                > 0;
-            // 既に monster に追いついていて、1撃じゃ死なない monster に goal されそうなら、 WIND!
-            if around_alive
-                && monster.pos.distance(&board.player.base) <= THREASHOLD_BASE_DAMAGE_RADIUS + MAX_MONSTER_VELOCITY
-                && solver.can_spell(board, true)
-            {
+            // 割り当てで wind を任された monster が goal されそうなら、WIND!
+            if solver.wind_assigned(board, hero_id) && solver.can_spell(board, true) {
                 let point = hero.pos * 2 - board.player.base;
-                solver.spell_count += 1;
                 Action::Wind {
                     point,
                     message: format!("[def]wind"),
                 }
-            } else if hero.shield_life == 0 && solver.can_spell(board, true) && solver.is_opponent_speller {
-                solver.spell_count += 1;
+            } else if solver.needs_self_shield(board, hero) && solver.can_spell(board, true) {
                 Action::Shield {
                     entity_id: hero.id,
                     message: format!("shield self!"),
                 }
             } else if hero.pos.distance(&monster.pos) <= ATTACK_HIT_RADIUS {
                 // 攻撃が当たるなら、マナの収集効率が良い場所を見つける
-                let candidate = self.enumerate_multiple_hit_with_target(board, hero_id, monster);
+                let candidate = farming::target_hit_point_list(board, &hero.pos, monster);
                 assert!(!candidate.is_empty());
+                solver.annotate_candidate(hero_id, &candidate);
 
                 Action::Move {
-                    point: candidate[0].1,
-                    message: format!("[def]{}attack", candidate[0].0),
+                    point: candidate[0].point,
+                    message: format!("[def]{}attack", candidate[0].hit),
                 }
             } else {
-                let (_, point) = self.shortest_move(&monster, &hero.pos);
+                let point = solver
+                    .assignment
+                    .intercept(hero_id)
+                    .unwrap_or_else(|| geometry::intercept_point(board, monster, &hero.pos));
                 Action::Move {
                     point,
                     message: format!("[def]shortest"),
                 }
             }
-        } else {
+        } else if let Some((monster, point)) = solver.incoming_monster(hero_id) {
+            // 見えていないけど自陣に向かってくる monster を待ち構える
             Action::Move {
-                point: self.home,
-                message: format!("[def]go home"),
-            }
-        }
-    }
-
-    fn shortest_move(&self, m: &Monster, pos: &Point) -> (i32, Point) {
-        // FIXME: 共通化
-        (1, m.next_pos())
-    }
-
-    /// 攻撃したい target は含んだ状態で、可能な限り hit が多い位置を探索する
-    fn enumerate_multiple_hit_with_target(
-        &self,
-        board: &Board,
-        hero_id: usize,
-        target: &Monster,
-    ) -> Vec<(usize, Point)> {
-        let mut ret = vec![];
-
-        // 3点 hit
-        for m1 in board.monster_list.iter() {
-            for m2 in board.monster_list.iter().filter(|m| m.id != m1.id) {
-                for m3 in board.monster_list.iter().filter(|m| m.id != m1.id && m.id != m2.id) {
-                    if m1.pos.distance(&m2.pos) <= 3 * ATTACK_HIT_RADIUS / 2
-                        && m1.pos.distance(&m3.pos) <= 3 * ATTACK_HIT_RADIUS / 2
-                        && m2.pos.distance(&m3.pos) <= 3 * ATTACK_HIT_RADIUS / 2
-                        && (m1.id == target.id || m2.id == target.id || m3.id == target.id)
-                    {
-                        // m1, m2, m3 間の各距離が全部 3/2R 以内なら、3点の重心に行くと3体に当たる
-                        let middle = (m1.pos + m2.pos + m3.pos) / 3;
-                        ret.push((3, middle));
-                    }
-                }
+                point,
+                message: format!("[def]wait m{}", monster),
             }
-        }
-
-        // 2点 hit
-        for m1 in board.monster_list.iter() {
-            for m2 in board.monster_list.iter().filter(|m| m.id != m1.id) {
-                if m1.pos.distance(&m2.pos) <= 2 * ATTACK_HIT_RADIUS && (m1.id == target.id || m2.id == target.id) {
-                    let middle = (m1.pos + m2.pos) / 2;
-                    ret.push((2, middle));
-                }
+        } else if let Some(threat) = solver.cover(hero_id) {
+            // 視界の外から wind で押し込まれそうな monster を待ち構える
+            Action::Move {
+                point: solver.dodge(board, hero, threat.landing),
+                message: format!("[def]cover m{}", threat.monster_id),
             }
-        }
-
-        // 1点 hit
-        for m1 in board.monster_list.iter() {
-            if m1.id == target.id {
-                ret.push((1, m1.pos));
+        } else {
+            // 手が空いている時は、相手の wind で飛ばされない所で待つ
+            Action::Move {
+                point: solver.dodge(board, hero, self.home),
+                message: format!("[def]go home"),
             }
         }
-        ret
     }
 }
 
@@ -636,17 +544,31 @@ enum HeroState {
     Defender(DefenderInfo),
 }
 
-#[derive(Clone, Debug)]
-enum OpponentStrategyType {
-    NotEstimated,
-    CompletelyDefense,
+impl HeroState {
+    fn name(&self) -> &'static str {
+        match self {
+            HeroState::CollectMana(_) => "CollectMana",
+            HeroState::Attacker(_) => "Attacker",
+            HeroState::MidFielder(_) => "MidFielder",
+            HeroState::Defender(_) => "Defender",
+        }
+    }
+
+    /// 役割の持ち場
+    fn home(&self) -> Point {
+        match self {
+            HeroState::CollectMana(info) => info.home,
+            HeroState::Attacker(info) => info.home,
+            HeroState::MidFielder(info) => info.home,
+            HeroState::Defender(info) => info.home,
+        }
+    }
 }
 
 #[derive(Clone, Debug)]
 struct SolverState {
     // 相手が自分の hero に対して一度でも妨害呪文をかけてきたか
     is_opponent_speller: bool,
-    spell_count: i32,
     // mid fielder が何回 control したか
     // これを見て attacker が妨害工作をするタイミングを決める
     midfielder_countrol_count: i32,
@@ -654,28 +576,172 @@ struct SolverState {
     strategy_changed: bool,
 
     // 相手の戦略概要の推測
-    opponent_strategy: OpponentStrategyType,
+    opponent: OpponentClassifier,
+    // 相手のマナの推測
+    mana: ManaEstimator,
+    // どの hero の spell にマナを回すか
+    planner: ManaPlanner,
+    // 防衛する hero がどの monster を追うか
+    assignment: Assignment,
+    // マナ集めの hero の数ターン分の計画
+    farm_plan: Vec<FarmPlan>,
+    // attacker が wind で monster を押し込む計画
+    wind_plan: Option<WindPlan>,
+    // 視界の外から自陣に入ってくる monster と、入ってくる位置で待つ hero
+    incoming: Option<(usize, i32, Point)>,
+    // 視界の外から押し込まれそうな monster と、その先で待つ hero
+    cover: Option<(usize, WindThreat)>,
+    // 持ち時間を超えそうになった記録
+    time: TimeManager,
+    // sudden death に入ってからの方針
+    endgame: Option<EndgamePlan>,
+    // 今の役割の組と、どの hero がどの役割か
+    formation: Formation,
+    role: RoleManager,
+    params: Params,
 
     prev_hero_pos: Vec<Point>,
+
+    // 視界の外に出た monster, 相手 hero の記憶
+    tracker: Tracker,
+
+    // visualizer 向けのデバッグ情報を集めるか
+    annotation_enabled: bool,
+    annotation_list: Vec<Annotation>,
 }
 
 impl SolverState {
-    fn can_spell(&self, board: &Board, has_priority: bool) -> bool {
-        if has_priority {
-            board.player.mana - self.spell_count * 10 >= 10
+    /// has_priority な spell (防衛の wind など) は、他の hero の spell より先にマナを割り当てる
+    fn can_spell(&mut self, board: &Board, has_priority: bool) -> bool {
+        let urgency = if has_priority {
+            Urgency::Emergency
         } else {
-            board.player.mana - (2 + self.spell_count) * 10 >= 10
+            Urgency::Normal
+        };
+        self.planner.request(board, urgency)
+    }
+
+    /// 割り当てで hero_id の hero が wind で飛ばすことになった monster が、base の目の前まで来て wind が届くか
+    ///
+    /// 早く飛ばすと殴ってマナを稼ぐ機会が減るので、ぎりぎりまで待つ
+    fn wind_assigned(&self, board: &Board, hero_id: usize) -> bool {
+        let hero = &board.player.hero_list[hero_id];
+        self.assignment
+            .wind_target(hero_id)
+            .and_then(|id| board.monster(id))
+            .is_some_and(|m| {
+                m.shield_life == 0
+                    && hero.pos.distance(&m.pos) <= WIND_RADIUS
+                    && m.pos.distance(&board.player.base) <= THREASHOLD_BASE_DAMAGE_RADIUS + MAX_MONSTER_VELOCITY
+            })
+    }
+
+    /// 相手が spell を使ってくる時に、hero が自分に shield をかけるべきか
+    fn needs_self_shield(&self, board: &Board, hero: &Hero) -> bool {
+        self.is_opponent_speller && shield::hero_needs_shield(board, hero, self.mana.estimate())
+    }
+
+    /// 手の空いている hero が to で待つ時に、相手の wind を避ける位置
+    fn dodge(&self, board: &Board, hero: &Hero, to: Point) -> Point {
+        counter::dodge_point(board, hero, to, self.mana.estimate())
+    }
+
+    /// hero_id の hero が待ち構える、視界の外から押し込まれそうな monster
+    fn cover(&self, hero_id: usize) -> Option<&WindThreat> {
+        self.cover
+            .as_ref()
+            .filter(|(id, _)| *id == hero_id)
+            .map(|(_, threat)| threat)
+    }
+
+    /// 緊急でない spell を撃つ時に残しておく spell の回数
+    ///
+    /// 数ターン以内に base に届く monster と、相手が撃てる spell の多い方に備える
+    fn reserve_spell(&self, board: &Board) -> i32 {
+        let emergency = planner::emergency_count(board, &self.tracker);
+        // 最後に残ったマナは勝敗に関係ないので、攻める時は使いきる
+        if let Some(EndgamePlan::Attack { .. }) = self.endgame {
+            return emergency;
         }
+        emergency.max(self.mana.reserve(self.opponent.params().spell_reserve))
     }
 
-    fn dump(&self) {
-        eprintln!("  is_opponent_speller: {}", self.is_opponent_speller);
-        eprintln!("  spell_count: {}", self.spell_count);
-        eprintln!("  midfielder_control_count: {}", self.midfielder_countrol_count);
-        eprintln!("  strategy_changed: {}", self.strategy_changed);
-        eprintln!("  prev_hero_pos: ");
+    /// 2回目の判断をする時間が無い時は、1回目の action を使う
+    ///
+    /// マナが割り当てられなかった spell は撃てないので、その場で待つ
+    fn fallback_action_list(&self, requested: Vec<Action>) -> Vec<Action> {
+        requested
+            .into_iter()
+            .enumerate()
+            .map(|(hero_id, action)| match action {
+                Action::Wind { .. } | Action::Shield { .. } | Action::Control { .. }
+                    if !self.planner.granted(hero_id) =>
+                {
+                    Action::Wait {
+                        message: "[time]wait".to_string(),
+                    }
+                }
+                _ => action,
+            })
+            .collect()
+    }
+
+    /// hero_id の hero が待ち構える、視界の外から PREPOSITION_TURN 以内に自陣に入ってくる monster の id と、入ってくる位置
+    fn incoming_monster(&self, hero_id: usize) -> Option<(i32, Point)> {
+        self.incoming
+            .filter(|(id, _, _)| *id == hero_id)
+            .map(|(_, monster_id, point)| (monster_id, point))
+    }
+
+    fn farm_plan(&self, hero_id: usize) -> Option<&FarmPlan> {
+        self.farm_plan.iter().find(|p| p.hero_id == hero_id)
+    }
+
+    /// annotation が有効な時だけ f を評価して積む
+    fn annotate(&mut self, f: impl FnOnce() -> Annotation) {
+        if self.annotation_enabled {
+            self.annotation_list.push(f());
+        }
+    }
+
+    fn annotate_candidate(&mut self, hero_id: usize, candidate: &[HitPoint]) {
+        for h in candidate.iter() {
+            self.annotate(|| Annotation::Candidate {
+                point: h.point,
+                score: h.score,
+                label: format!("h{}", hero_id),
+            });
+        }
+    }
+
+    fn describe(&self) -> Vec<String> {
+        let mut ret = vec![
+            format!("is_opponent_speller: {}", self.is_opponent_speller),
+            format!("midfielder_control_count: {}", self.midfielder_countrol_count),
+            format!("strategy_changed: {}", self.strategy_changed),
+            format!("opponent_strategy: {:?}", self.opponent.strategy()),
+            format!("endgame: {:?}", self.endgame),
+            format!("formation: {:?}", self.formation),
+            self.role.describe(),
+            self.mana.describe(),
+            self.time.describe(),
+            self.wind_plan
+                .as_ref()
+                .map_or("wind chain: none".to_string(), |p| p.describe()),
+            format!("incoming: {:?}", self.incoming),
+            format!("cover: {:?}", self.cover),
+            "prev_hero_pos: ".to_string(),
+        ];
+        ret.extend(self.planner.describe());
         for p in self.prev_hero_pos.iter() {
-            eprintln!("    prev_pos: {:?}", p);
+            ret.push(format!("  prev_pos: {:?}", p));
+        }
+        ret
+    }
+
+    fn dump(&self) {
+        for line in self.describe() {
+            eprintln!("  {}", line);
         }
     }
 }
@@ -684,6 +750,17 @@ impl SolverState {
 pub struct Solver {
     hero_state: Vec<HeroState>,
     solver_state: SolverState,
+    // Some なら、role 毎の判断を hint にして探索で action を選び直す
+    search: Option<Search>,
+    // false なら、毎ターンの盤面や状態を stderr に書かない
+    log_enabled: bool,
+}
+
+/// role 毎の判断の代わりに使う探索
+#[derive(Clone)]
+enum Search {
+    Beam(BeamSearch),
+    Mcts(Mcts),
 }
 
 const MAX_X: i32 = 17630;
@@ -692,56 +769,282 @@ const DETECT_BASE_RADIUS: i32 = 5000;
 const WIND_RADIUS: i32 = 1280;
 const CONTROL_RADIUS: i32 = 2200;
 const SHIELD_RADIUS: i32 = 2200;
-const MAX_PLAYER_VELOCITY: i32 = 800;
 const MAX_MONSTER_VELOCITY: i32 = 400;
 const THREASHOLD_BASE_DAMAGE_RADIUS: i32 = 300;
-const PLAYER_DAMAGE: i32 = 2;
-const VELOCITY_DIFF: i32 = MAX_PLAYER_VELOCITY - MAX_MONSTER_VELOCITY;
 const ATTACK_HIT_RADIUS: i32 = 800;
 const HERO_RECOGNIZABLE_RADIUS: i32 = 2200;
+// 見えていない monster を待ち構えに行くのは、自陣に入ってくるまでのターン数がこれ以下の時
+const PREPOSITION_TURN: i32 = 10;
 
 impl Solver {
     fn hero_size(&self) -> usize {
         self.hero_state.len()
     }
 
+    /// solve の度に、判断材料を Annotation として記録するようにする
+    pub fn enable_annotation(&mut self) {
+        self.solver_state.annotation_enabled = true;
+    }
+
+    /// role 毎の判断の代わりに、beam search で選んだ action を返すようにする
+    pub fn enable_beam_search(&mut self) {
+        self.search = Some(Search::Beam(BeamSearch::new()));
+    }
+
+    /// role 毎の判断の代わりに、MCTS で選んだ action を返すようにする
+    pub fn enable_mcts(&mut self) {
+        self.search = Some(Search::Mcts(Mcts::new()));
+    }
+
+    /// 毎ターンの盤面や状態を stderr に書かないようにする (たくさん試合をする時用)
+    pub fn disable_log(&mut self) {
+        self.log_enabled = false;
+    }
+
+    /// 直前の solve で記録された Annotation
+    pub fn annotation_list(&self) -> &[Annotation] {
+        &self.solver_state.annotation_list
+    }
+
+    fn annotate_action(&mut self, board: &Board, action_list: &[Action]) {
+        // describe などの文字列を作るのも重いので、無効な時は何もしない
+        if !self.solver_state.annotation_enabled {
+            return;
+        }
+        for (hero_id, (hero, action)) in board.player.hero_list.iter().zip(action_list.iter()).enumerate() {
+            let role = self.hero_state[hero_id].name();
+            self.solver_state.annotate(|| Annotation::Role {
+                hero_id,
+                role: role.to_string(),
+            });
+
+            let (target, radius, label) = match action {
+                Action::Wait { .. } => continue,
+                Action::Move { point, message } => (*point, None, message),
+                Action::Wind { point, message } => (*point, Some(WIND_RADIUS), message),
+                Action::Shield { entity_id, message } | Action::Control { entity_id, message, .. } => {
+                    let target = board
+                        .monster_list
+                        .iter()
+                        .map(|m| (m.id, m.pos))
+                        .chain(board.player.hero_list.iter().map(|h| (h.id, h.pos)))
+                        .chain(board.opponent.hero_list.iter().map(|h| (h.id, h.pos)))
+                        .find(|(id, _)| id == entity_id);
+                    match target {
+                        Some((_, pos)) => (pos, Some(CONTROL_RADIUS), message),
+                        None => continue,
+                    }
+                }
+            };
+            if let Some(radius) = radius {
+                self.solver_state.annotate(|| Annotation::Circle {
+                    center: hero.pos,
+                    radius,
+                    label: label.clone(),
+                });
+            }
+            self.solver_state.annotate(|| Annotation::Line {
+                from: hero.pos,
+                to: target,
+                label: label.clone(),
+            });
+        }
+
+        // 視界の外の記憶
+        let tracker = &self.solver_state.tracker;
+        let mut annotation_list = vec![];
+        for t in tracker.unseen_monster_list(board) {
+            annotation_list.push(Annotation::Candidate {
+                point: t.monster.pos,
+                score: t.age(board.turn) as i32,
+                label: format!("m{}", t.monster.id),
+            });
+        }
+        for t in tracker.unseen_opponent_hero_list(board) {
+            annotation_list.push(Annotation::Circle {
+                center: t.hero.pos,
+                radius: t.uncertainty(board.turn),
+                label: format!("op{}", t.hero.id),
+            });
+        }
+        for annotation in annotation_list {
+            self.solver_state.annotate(|| annotation);
+        }
+
+        for text in self.solver_state.describe() {
+            self.solver_state.annotate(|| Annotation::Text { text });
+        }
+    }
+
     pub fn new(base_pos: &Point, hero_size: usize) -> Solver {
+        Self::with_params(base_pos, hero_size, Params::default())
+    }
+
+    pub fn with_params(base_pos: &Point, hero_size: usize, params: Params) -> Solver {
         Solver {
             hero_state: (0..hero_size)
-                .map(|hero_id| HeroState::CollectMana(CollectManaInfo::new(base_pos, hero_id)))
+                .map(|hero_id| HeroState::CollectMana(CollectManaInfo::new(base_pos, hero_id, &params)))
                 .collect::<Vec<_>>(),
             solver_state: SolverState {
                 is_opponent_speller: false,
-                spell_count: 0,
                 midfielder_countrol_count: 0,
                 strategy_changed: false,
-                opponent_strategy: OpponentStrategyType::NotEstimated,
+                opponent: OpponentClassifier::with_params(params),
+                mana: ManaEstimator::default(),
+                planner: ManaPlanner::new(),
+                assignment: Assignment::default(),
+                farm_plan: vec![],
+                wind_plan: None,
+                incoming: None,
+                cover: None,
+                time: TimeManager::default(),
+                endgame: None,
+                formation: Formation::Farm,
+                role: RoleManager::default(),
+                params,
                 prev_hero_pos: vec![Point { x: 0, y: 0 }; 3],
+                tracker: Tracker::default(),
+                annotation_enabled: false,
+                annotation_list: vec![],
             },
+            search: None,
+            log_enabled: true,
         }
     }
 
-    pub fn solve(&mut self, board: &Board) -> Vec<Action> {
-        let start = Instant::now();
+    /// sudden death に入ったら、残りのターンの方針を決める (役割は update_role で組み直す)
+    ///
+    /// 見込みが少し動いたくらいでは、前のターンの方針を続ける
+    fn update_endgame(&mut self, board: &Board) {
+        if board.turn < endgame::SUDDEN_DEATH_TURN {
+            return;
+        }
+        let forecast = endgame::forecast(board);
+        let wild_lead = self.solver_state.mana.wild_lead();
+        let plan = endgame::replan(self.solver_state.endgame, board, &forecast, wild_lead);
+        self.solver_state.endgame = Some(plan);
+        self.solver_state.strategy_changed = true;
+    }
 
-        eprintln!("solver_state:");
-        self.solver_state.dump();
-        eprintln!("self hero");
-        eprintln!("  base: {:?}", board.player.base);
-        for h in board.player.hero_list.iter() {
-            eprintln!("{:?}", h);
+    /// 今の盤面で欲しい役割の組を決めて、hero を割り当て直す
+    ///
+    /// 役割の状態を保つために、持ち場か状態の種類が変わった hero だけ状態を作り直す
+    fn update_role(&mut self, board: &Board) {
+        let mana = board.player.mana;
+        let pair_attack_mana = self.solver_state.params.pair_attack_mana;
+        let formation = if let Some(plan) = self.solver_state.endgame {
+            Formation::Endgame(plan)
+        } else if !self.solver_state.strategy_changed {
+            Formation::Farm
+        } else if self.solver_state.formation == Formation::PairAttack && mana >= pair_attack_mana / 2
+            || mana >= pair_attack_mana
+        {
+            // 一度揃って押し込み始めたら、マナが半分を切るまでは続ける
+            Formation::PairAttack
+        } else {
+            Formation::Attack
+        };
+
+        let params = self.solver_state.params;
+        let policy = self.solver_state.opponent.params();
+        let base = board.player.base;
+        let role_list = formation.role_list();
+        let slot_list = role_list
+            .iter()
+            .enumerate()
+            .map(|(i, &role)| {
+                let index = role_list[..i].iter().filter(|r| **r == role).count();
+                let point = Self::role_state(&base, &params, &policy, formation, role, index).home();
+                Slot { role, index, point }
+            })
+            .collect::<Vec<_>>();
+        let hero_list = board.player.hero_list.iter().map(|h| h.pos).collect::<Vec<_>>();
+        let busy = (0..hero_list.len())
+            .map(|hero_id| self.solver_state.assignment.target(hero_id).is_some())
+            .collect::<Vec<_>>();
+        let prev_slot_list = (0..hero_list.len())
+            .map(|hero_id| self.solver_state.role.slot(hero_id).copied())
+            .collect::<Vec<_>>();
+        let assigned = self.solver_state.role.assign(&hero_list, &slot_list, &busy);
+
+        self.solver_state.formation = formation;
+        for (hero_id, slot) in assigned.iter().enumerate() {
+            // マナ集めの組の守りは CollectMana なので、組が変わると持ち場が同じでも Defender に作り直す
+            let state = Self::role_state(&base, &params, &policy, formation, slot.role, slot.index);
+            if !prev_slot_list[hero_id].is_some_and(|prev| prev.is_same(slot))
+                || state.name() != self.hero_state[hero_id].name()
+            {
+                self.hero_state[hero_id] = state;
+            }
         }
-        eprintln!("opponent hero");
-        eprintln!("  base: {:?}", board.opponent.base);
-        for h in board.opponent.hero_list.iter() {
-            eprintln!("{:?}", h);
+    }
+
+    /// formation の中で role の index 番目になった hero の状態
+    fn role_state(
+        base: &Point,
+        params: &Params,
+        policy: &PolicyParams,
+        formation: Formation,
+        role: Role,
+        index: usize,
+    ) -> HeroState {
+        match role {
+            Role::Farmer => HeroState::CollectMana(CollectManaInfo::new(base, index, params)),
+            // マナを集めている間は、守りもマナ集めの hero の 1体として動く
+            Role::Defender if formation == Formation::Farm => {
+                HeroState::CollectMana(CollectManaInfo::new(base, 2, params))
+            }
+            Role::Defender => HeroState::Defender(DefenderInfo::new(policy, index)),
+            Role::MidFielder => HeroState::MidFielder(MidFielderInfo::new(params)),
+            Role::Attacker | Role::PairAttack => HeroState::Attacker(AttackerInfo::new(params)),
         }
-        eprintln!("monster");
-        for m in board.monster_list.iter() {
-            eprintln!("{} {:?}", m.pos.distance(&board.player.hero_list[2].pos), m);
+    }
+
+    fn decide(hero_state: &mut [HeroState], solver_state: &mut SolverState, board: &Board) -> Vec<Action> {
+        hero_state
+            .iter_mut()
+            .enumerate()
+            .map(|(hero_id, state)| -> Action {
+                solver_state.planner.set_current_hero(hero_id);
+                match state {
+                    HeroState::CollectMana(info) => info.action(board, hero_id, solver_state),
+                    HeroState::Attacker(info) => info.action(board, hero_id, solver_state),
+                    HeroState::MidFielder(info) => info.action(board, hero_id, solver_state),
+                    HeroState::Defender(info) => info.action(board, hero_id, solver_state),
+                }
+            })
+            .collect()
+    }
+
+    pub fn solve(&mut self, board: &Board) -> Vec<Action> {
+        let timer = TurnTimer::start(board.turn);
+
+        if self.log_enabled {
+            eprintln!("solver_state:");
+            self.solver_state.dump();
+            eprintln!("self hero");
+            eprintln!("  base: {:?}", board.player.base);
+            for h in board.player.hero_list.iter() {
+                eprintln!("{:?}", h);
+            }
+            eprintln!("opponent hero");
+            eprintln!("  base: {:?}", board.opponent.base);
+            for h in board.opponent.hero_list.iter() {
+                eprintln!("{:?}", h);
+            }
+            eprintln!("monster");
+            for m in board.monster_list.iter() {
+                eprintln!("{} {:?}", m.pos.distance(&board.player.hero_list[2].pos), m);
+            }
         }
 
-        self.solver_state.spell_count = 0;
+        self.solver_state.annotation_list.clear();
+        self.solver_state.tracker.update(board);
+        self.solver_state.opponent.update(board);
+        self.solver_state.mana.update(board);
+
+        self.update_endgame(board);
+        self.update_role(board);
 
         if board.turn == 1 {
             for hero_id in 0..3 {
@@ -754,34 +1057,172 @@ impl Solver {
                 self.solver_state.is_opponent_speller = true;
             }
             // WIND を使われた
-            if self.solver_state.prev_hero_pos[hero_id].distance(&hero.pos) > 1200 {
+            if self.solver_state.prev_hero_pos[hero_id].distance(&hero.pos)
+                > self.solver_state.params.wind_detect_distance
+            {
                 self.solver_state.is_opponent_speller = true;
             }
         }
 
-        let ret = (0..self.hero_size())
-            .map(|hero_id| -> Action {
-                match &mut self.hero_state[hero_id] {
-                    HeroState::CollectMana(info) => info.action(board, hero_id, &mut self.solver_state),
-                    HeroState::Attacker(info) => info.action(board, hero_id, &mut self.solver_state),
-                    HeroState::MidFielder(info) => info.action(board, hero_id, &mut self.solver_state),
-                    HeroState::Defender(info) => info.action(board, hero_id, &mut self.solver_state),
-                }
+        let defender_list = self
+            .hero_state
+            .iter()
+            .enumerate()
+            .filter_map(|(hero_id, state)| match state {
+                HeroState::CollectMana(info) => Some(Defender {
+                    hero_id,
+                    dedicated: info.guards_base(),
+                }),
+                HeroState::Defender(_) => Some(Defender {
+                    hero_id,
+                    dedicated: true,
+                }),
+                HeroState::Attacker(_) | HeroState::MidFielder(_) => None,
             })
             .collect::<Vec<_>>();
+        self.solver_state.assignment = assignment::assign(board, &defender_list);
+
+        // 視界の外から自陣に入ってくる monster は、手の空いている防衛役のうち一番近い 1体が待ち構える
+        // 残りは持ち場で待つ
+        let assignment = &self.solver_state.assignment;
+        let idle_list = defender_list
+            .iter()
+            .filter(|d| d.dedicated && assignment.target(d.hero_id).is_none())
+            .map(|d| d.hero_id)
+            .collect::<Vec<_>>();
+        self.solver_state.incoming = self
+            .solver_state
+            .tracker
+            .incoming_monster(board)
+            .filter(|(_, _, turn)| *turn <= PREPOSITION_TURN)
+            .and_then(|(t, point, _)| {
+                idle_list
+                    .iter()
+                    .min_by_key(|&&hero_id| board.player.hero_list[hero_id].pos.distance(&point))
+                    .map(|&hero_id| (hero_id, t.monster.id, point))
+            });
+
+        // 視界の外から押し込まれそうな monster の先に、それ以外の手の空いている防衛役を 1体置いておく
+        let belief = self.solver_state.tracker.belief(board);
+        let opponent_mana = self.solver_state.mana.estimate();
+        let incoming = self.solver_state.incoming;
+        self.solver_state.cover = counter::wind_threat_list(board, &belief, opponent_mana)
+            .first()
+            .and_then(|threat| {
+                idle_list
+                    .iter()
+                    .filter(|&&hero_id| incoming.map(|(id, _, _)| id) != Some(hero_id))
+                    .min_by_key(|&&hero_id| board.player.hero_list[hero_id].pos.distance(&threat.landing))
+                    .map(|&hero_id| (hero_id, *threat))
+            });
+
+        // 防衛を手伝わない CollectMana の hero は、まとめてマナ集めの計画を立てる
+        let farmer_list = self
+            .hero_state
+            .iter()
+            .enumerate()
+            .filter(|(hero_id, state)| {
+                matches!(state, HeroState::CollectMana(info) if !info.guards_base())
+                    && self.solver_state.assignment.intercept(*hero_id).is_none()
+            })
+            .map(|(hero_id, _)| hero_id)
+            .collect::<Vec<_>>();
+        let base = board.player.base;
+        // wild mana を稼ぐ時は、自陣の外ならどこでも良い
+        let farm_distance = if self.solver_state.endgame == Some(EndgamePlan::Farm) {
+            DETECT_BASE_RADIUS
+        } else {
+            self.solver_state.params.farm_distance
+        };
+        self.solver_state.farm_plan = if timer.is_over(Phase::Prepare) {
+            // 計画を立てる時間が無ければ、マナ集めの hero は home に戻る
+            let message = self
+                .solver_state
+                .time
+                .record_skip(board.turn, Phase::Prepare, timer.elapsed());
+            if self.log_enabled {
+                eprintln!("{}", message);
+            }
+            vec![]
+        } else {
+            farming::plan(board, &farmer_list, |p| base.distance(p) > farm_distance)
+        };
+
+        // 攻める組毎に、成功しそうなら wind で monster を押し込む (PairAttack は 2体揃って撃つ)
+        let spell_mana = board.player.mana - simulator::MANA_TO_SPELL * self.solver_state.reserve_spell(board);
+        let success_percent = self.solver_state.params.wind_chain_success_percent as f64;
+        self.solver_state.wind_plan = self
+            .solver_state
+            .role
+            .group_list()
+            .iter()
+            .filter(|g| matches!(g.role, Role::Attacker | Role::PairAttack))
+            .filter_map(|g| wind_chain::best_plan(board, &g.hero_list, spell_mana, opponent_mana))
+            .filter(|p| p.success_probability * 100.0 >= success_percent)
+            .fold(None, |best, p| {
+                if wind_chain::is_better(&p, best.as_ref()) {
+                    Some(p)
+                } else {
+                    best
+                }
+            });
+
+        // 1回目: 全員が spell を撃てるものとして action を決め、spell の要求を集める
+        // role の状態を進めないように、複製の上で決める
+        let mut hero_state = self.hero_state.clone();
+        let mut solver_state = self.solver_state.clone();
+        solver_state.planner.begin_collect(self.hero_size());
+        let requested = Self::decide(&mut hero_state, &mut solver_state, board);
+
+        // 緊急の spell から順にマナを割り当てて、2回目で割り当て通りに action を決める
+        let reserve_spell = self.solver_state.reserve_spell(board);
+        self.solver_state.planner = solver_state.planner;
+        self.solver_state.planner.allocate(board, &requested, reserve_spell);
+        let mut ret = if timer.is_over(Phase::Role) {
+            let message = self
+                .solver_state
+                .time
+                .record_skip(board.turn, Phase::Role, timer.elapsed());
+            if self.log_enabled {
+                eprintln!("{}", message);
+            }
+            self.solver_state.fallback_action_list(requested)
+        } else {
+            Self::decide(&mut self.hero_state, &mut self.solver_state, board)
+        };
+
+        if let Some(search) = self.search.as_ref().filter(|_| !timer.is_over(Phase::Search)) {
+            let belief = self.solver_state.tracker.belief(board);
+            let is_time_over = || timer.is_over(Phase::Search);
+            ret = match search {
+                Search::Beam(beam_search) => beam_search.search(board, &belief, &ret, is_time_over),
+                Search::Mcts(mcts) => mcts.search(board, &belief, &ret, is_time_over),
+            };
+        }
+
+        self.annotate_action(board, &ret);
+        self.solver_state.opponent.record_action(&ret);
+        self.solver_state.mana.record_action(&ret);
 
         // 相手に比べてマナがたくさんある || 十分マナが揃ったら攻撃態勢
-        if !self.solver_state.strategy_changed && board.player.mana >= 200
-            || (board.player.mana - board.opponent.mana >= 100)
+        let policy = self.solver_state.opponent.params();
+        let params = self.solver_state.params;
+        // 役割は次のターンの update_role で組み直す
+        if self.solver_state.endgame.is_none()
+            && (board.player.mana >= policy.attack_mana
+                || board.player.mana - board.opponent.mana >= params.mana_lead_to_attack)
         {
             self.solver_state.strategy_changed = true;
-            self.hero_state[0] = HeroState::Attacker(AttackerInfo::new());
-            self.hero_state[1] = HeroState::MidFielder(MidFielderInfo::new());
-            self.hero_state[2] = HeroState::Defender(DefenderInfo::new());
         }
 
-        let elapsed = (Instant::now() - start).as_millis();
-        eprintln!("elapsed: {}[ms]", elapsed);
+        let elapsed = timer.elapsed();
+        let overrun = self.solver_state.time.record_turn(board.turn, elapsed);
+        if self.log_enabled {
+            eprintln!("elapsed: {}[ms]", elapsed);
+            if let Some(message) = overrun {
+                eprintln!("{}", message);
+            }
+        }
 
         for hero_id in 0..3 {
             self.solver_state.prev_hero_pos[hero_id] = board.player.hero_list[hero_id].pos;
@@ -790,3 +1231,126 @@ impl Solver {
         ret
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_defender_home() {
+        let policy = params::DEFAULT_PARAMS.policy();
+        // 1体で守る時は対角線上で、defender_distance だけ離れる
+        let home = DefenderInfo::new(&policy, 0).home;
+        assert_eq!(home, Point { x: 3000, y: 3000 });
+        assert!((home.distance(&Point { x: 0, y: 0 }) - policy.defender_distance).abs() <= 1);
+        // 全員で守る時は、それぞれ違う所で待つ
+        let home_list = (0..3).map(|i| DefenderInfo::new(&policy, i).home).collect::<Vec<_>>();
+        for i in 0..3 {
+            for j in 0..i {
+                assert!(home_list[i].distance(&home_list[j]) > WIND_RADIUS);
+            }
+        }
+    }
+
+    #[test]
+    fn test_assigned_wind() {
+        let mut board = Board {
+            player: Player::new(),
+            opponent: Player::new(),
+            monster_list: vec![Monster {
+                id: 10,
+                pos: Point { x: 450, y: 450 },
+                shield_life: 0,
+                is_controlled: false,
+                health: 20,
+                v: Point { x: -283, y: -283 },
+                threat_state: MonsterThreatState::PlayerThreat,
+            }],
+            turn: 1,
+        };
+        board.player.mana = 100;
+        board.opponent.base = Point { x: MAX_X, y: MAX_Y };
+        board.player.hero_list = [
+            Point { x: 8000, y: 8000 },
+            Point { x: 9000, y: 7000 },
+            Point { x: 1200, y: 1000 },
+        ]
+        .iter()
+        .enumerate()
+        .map(|(id, &pos)| Hero {
+            id: id as i32,
+            pos,
+            shield_life: 0,
+            is_controlled: false,
+        })
+        .collect();
+
+        let mut solver = Solver::new(&board.player.base, 3);
+        solver.disable_log();
+        let action_list = solver.solve(&board);
+        // 殴りきれずに base に入るので、守りの hero が割り当て通りに wind で飛ばす
+        assert_eq!(solver.solver_state.assignment.wind_target(2), Some(10));
+        assert!(matches!(action_list[2], Action::Wind { .. }));
+    }
+
+    #[test]
+    fn test_endgame_incoming() {
+        let monster = |id: i32, pos: Point| Monster {
+            id,
+            pos,
+            shield_life: 0,
+            is_controlled: false,
+            health: 10,
+            v: ((Point { x: 0, y: 0 } - pos).to_f64().normalize() * 400.0).to::<i32>(),
+            threat_state: MonsterThreatState::PlayerThreatInTheFuture,
+        };
+        let mut board = Board {
+            player: Player::new(),
+            opponent: Player::new(),
+            monster_list: vec![monster(20, Point { x: 6000, y: 4000 })],
+            turn: 204,
+        };
+        board.player.health = 3;
+        board.opponent.health = 1;
+        board.opponent.base = Point { x: MAX_X, y: MAX_Y };
+        board.player.hero_list = [
+            Point { x: 1000, y: 3000 },
+            Point { x: 3000, y: 1000 },
+            Point { x: 2000, y: 2000 },
+        ]
+        .iter()
+        .enumerate()
+        .map(|(id, &pos)| Hero {
+            id: id as i32,
+            pos,
+            shield_life: 0,
+            is_controlled: false,
+        })
+        .collect();
+
+        let mut solver = Solver::new(&board.player.base, 3);
+        solver.disable_log();
+        // monster 20 は前のターンに見たきり、視界の外から自陣に向かってくる
+        solver.solver_state.tracker.update(&board);
+        board.turn = 205;
+        // 引力圏から出ていく monster がいるので、守りきる方針になる
+        board.monster_list = vec![Monster {
+            v: Point { x: 283, y: 283 },
+            threat_state: MonsterThreatState::NotThreat,
+            ..monster(10, Point { x: 3500, y: 3500 })
+        }];
+        let action_list = solver.solve(&board);
+        assert_eq!(solver.solver_state.endgame, Some(EndgamePlan::Defend));
+
+        // 全員で待ち構えずに、1体だけが入ってくる位置で待ち、残りは持ち場に戻る
+        let message_list = action_list
+            .iter()
+            .map(|action| match action {
+                Action::Move { message, .. } => message.clone(),
+                _ => String::new(),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(message_list.iter().filter(|m| m.contains("wait m20")).count(), 1);
+        assert!(message_list.iter().any(|m| m.contains("go home")));
+    }
+}
@@ -0,0 +1,176 @@
+//! 相手のマナの推測
+//!
+//! 見えている monster を相手 hero が殴った分だけ増やし、見えた spell の分だけ減らす
+//! ゲームから相手のマナが送られてきた時は、その値に合わせ直す
+//! 220 ターン目の tiebreak に使う、自陣の外で稼いだマナ (wild mana) も両者分数えておく
+
+use simulator::inout::*;
+use simulator::{BASE_ATTRACTION_RADIUS, HERO_ATTACK_RADIUS, MANA_GAIN_TO_ATTACK, MANA_TO_SPELL};
+
+use crate::opponent::SpellObservation;
+
+#[derive(Clone, Debug, Default)]
+pub struct ManaEstimator {
+    estimate: i32,
+    // 見えた範囲で、相手が得た・使ったマナの合計
+    gained: i32,
+    spent: i32,
+    // 自陣の外で稼いだマナ (自分, 相手)
+    wild: [i32; 2],
+
+    prev_board: Option<Board>,
+    prev_action_list: Vec<Action>,
+}
+
+impl ManaEstimator {
+    /// 毎ターン、solve の最初に呼ぶ
+    pub fn update(&mut self, board: &Board) {
+        if let Some(prev) = self.prev_board.take() {
+            let (hit, wild_hit) = Self::opponent_hit_count(&prev, board);
+            let gain = MANA_GAIN_TO_ATTACK * hit;
+            let spend = MANA_TO_SPELL * SpellObservation::new(&prev, board, &self.prev_action_list).total();
+            self.wild[0] += MANA_GAIN_TO_ATTACK * Self::self_wild_hit_count(&prev, board);
+            // 相手陣の中で殴ったのが見えた分以外は、見えないところで稼いだ wild mana とみなす (多めに見積もる)
+            self.wild[1] += if prev.opponent.mana > 0 && board.opponent.mana > 0 {
+                (board.opponent.mana - prev.opponent.mana + spend - MANA_GAIN_TO_ATTACK * (hit - wild_hit)).max(0)
+            } else {
+                MANA_GAIN_TO_ATTACK * wild_hit
+            };
+            self.gained += gain;
+            self.spent += spend;
+            self.estimate = (self.estimate + gain - spend).max(0);
+        }
+        // 0 の時は送られてこなかったものとして、推測を使い続ける
+        if board.opponent.mana > 0 {
+            self.estimate = board.opponent.mana;
+        }
+        self.prev_board = Some(board.clone());
+    }
+
+    /// 今ターンに自分が出した action を覚えておく
+    pub fn record_action(&mut self, action_list: &[Action]) {
+        self.prev_action_list = action_list.to_vec();
+    }
+
+    pub fn estimate(&self) -> i32 {
+        self.estimate
+    }
+
+    /// 相手が今撃てる spell の回数
+    pub fn affordable_spell(&self) -> i32 {
+        self.estimate / MANA_TO_SPELL
+    }
+
+    /// 相手の spell に備えて残しておく spell の回数 (max_reserve が上限)
+    ///
+    /// 相手が撃てない分まで取っておく必要はない
+    pub fn reserve(&self, max_reserve: i32) -> i32 {
+        self.affordable_spell().min(max_reserve)
+    }
+
+    /// 自分の wild mana から相手の分を引いたもの
+    pub fn wild_lead(&self) -> i32 {
+        self.wild[0] - self.wild[1]
+    }
+
+    pub fn describe(&self) -> String {
+        format!(
+            "opponent mana: {} (gained {}, spent {}), wild mana: {} vs {}",
+            self.estimate, self.gained, self.spent, self.wild[0], self.wild[1]
+        )
+    }
+
+    /// 見えている monster が、自分の hero 以外から受けた攻撃の回数と、そのうち相手陣の外で受けた回数
+    fn opponent_hit_count(prev: &Board, board: &Board) -> (i32, i32) {
+        board
+            .monster_list
+            .iter()
+            .filter_map(|m| prev.monster(m.id).map(|prev_m| (prev_m, m)))
+            .map(|(prev_m, m)| {
+                let hit = (prev_m.health - m.health) / MANA_GAIN_TO_ATTACK;
+                // hero は monster が動く前に殴るので、前の位置で数える
+                let self_hit = Self::self_hit_count(board, prev_m);
+                let hit = (hit - self_hit).max(0);
+                if board.opponent.base.in_range(&prev_m.pos, BASE_ATTRACTION_RADIUS) {
+                    (hit, 0)
+                } else {
+                    (hit, hit)
+                }
+            })
+            .fold((0, 0), |(a, b), (c, d)| (a + c, b + d))
+    }
+
+    /// 自分の hero が自陣の外で殴った回数 (倒して見えなくなった monster も数える)
+    fn self_wild_hit_count(prev: &Board, board: &Board) -> i32 {
+        prev.monster_list
+            .iter()
+            .filter(|m| !board.player.base.in_range(&m.pos, BASE_ATTRACTION_RADIUS))
+            .map(|m| Self::self_hit_count(board, m))
+            .sum()
+    }
+
+    fn self_hit_count(board: &Board, prev_m: &Monster) -> i32 {
+        board
+            .player
+            .hero_list
+            .iter()
+            .filter(|h| h.pos.in_range(&prev_m.pos, HERO_ATTACK_RADIUS))
+            .count() as i32
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use simulator::{MAX_X, MAX_Y, SHIELD_EFFECTIVE_TURN};
+
+    fn board(turn: usize, opponent_mana: i32, monster_list: Vec<Monster>) -> Board {
+        let mut board = Board {
+            player: Player::new(),
+            opponent: Player::new(),
+            monster_list,
+            turn,
+        };
+        board.opponent.base = Point { x: MAX_X, y: MAX_Y };
+        board.opponent.mana = opponent_mana;
+        board.player.hero_list = (0..3)
+            .map(|id| Hero {
+                id,
+                pos: Point { x: 1000, y: 1000 },
+                shield_life: 0,
+                is_controlled: false,
+            })
+            .collect();
+        board
+    }
+
+    fn monster(health: i32, shield_life: i32) -> Monster {
+        Monster {
+            id: 10,
+            pos: Point { x: 9000, y: 4000 },
+            shield_life,
+            is_controlled: false,
+            health,
+            v: Point { x: 0, y: 0 },
+            threat_state: MonsterThreatState::NotThreat,
+        }
+    }
+
+    #[test]
+    fn test_estimate() {
+        let mut estimator = ManaEstimator::default();
+        estimator.update(&board(1, 30, vec![monster(20, 0)]));
+        assert_eq!(estimator.affordable_spell(), 3);
+
+        // 相手 hero 2体に殴られて +4、shield をかけて -10
+        estimator.update(&board(2, 0, vec![monster(16, SHIELD_EFFECTIVE_TURN)]));
+        assert_eq!(estimator.estimate(), 24);
+        assert_eq!(estimator.reserve(5), 2);
+        // どちらの陣からも遠いので、相手の wild mana になる
+        assert_eq!(estimator.wild_lead(), -4);
+
+        // 送られてきたら合わせる
+        estimator.update(&board(3, 50, vec![]));
+        assert_eq!(estimator.estimate(), 50);
+    }
+}
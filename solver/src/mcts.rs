@@ -0,0 +1,232 @@
+//! 同時手番の MCTS (decoupled UCT)
+//!
+//! node 毎に、自分と相手がそれぞれ独立に UCB1 で action の組を選び、その組で ForwardModel を 1ターン進める
+//! (GameState::next_state は Simulator::next_state と同じルールで進む)
+//! GameState は大きいので node には持たせず、毎回根から選んだ action で進め直す
+//! 新しい node からは、両者とも簡単な方策 (beam::default_command_list) で ROLLOUT_TURN ターン進めて評価する
+//!
+//! 時間になるまで何回でも繰り返せて、最後に根で一番多く選ばれた自分の action の組を返す
+
+use std::collections::HashMap;
+
+use simulator::inout::*;
+use simulator::state::{Command, GameState, HERO_SIZE};
+
+use crate::beam;
+use crate::forward::{self, Belief, ForwardModel};
+
+// 新しい node から、簡単な方策で進めるターン数
+const ROLLOUT_TURN: usize = 5;
+// これより深い node は作らずに評価する
+const MAX_DEPTH: usize = 10;
+// is_time_over が true にならなくても、この回数で打ち切る
+const MAX_ITERATION: usize = 20000;
+const EXPLORATION: f64 = 0.7;
+// 評価値の差を勝率っぽい [0, 1] に潰す時の尺度 (base の health 1 つ分くらいで大きく変わる)
+const REWARD_SCALE: f64 = 5000.0;
+// 本物の seed は分からないので、適当な seed の乱数で monster を湧かせる
+const SEED: u64 = 1;
+
+#[derive(Clone)]
+pub struct Mcts {
+    model: ForwardModel,
+}
+
+#[derive(Clone, Copy, Default)]
+struct Stat {
+    visit: u32,
+    // player から見た reward の合計
+    reward: f64,
+}
+
+struct Node {
+    depth: usize,
+    visit: u32,
+    // player 毎の action の組の候補と、その統計
+    action_list: [Vec<[Command; HERO_SIZE]>; 2],
+    stat_list: [Vec<Stat>; 2],
+    // (自分の action, 相手の action) -> 子 node の番号
+    child: HashMap<(usize, usize), usize>,
+}
+
+impl Node {
+    /// state は、この node の盤面
+    fn new(state: &GameState, depth: usize, hint: Option<&[Command; HERO_SIZE]>) -> Node {
+        let action_list = [
+            beam::joint_candidate_list(state, 0, hint),
+            beam::joint_candidate_list(state, 1, None),
+        ];
+        let stat_list = [
+            vec![Stat::default(); action_list[0].len()],
+            vec![Stat::default(); action_list[1].len()],
+        ];
+        Node {
+            depth,
+            visit: 0,
+            action_list,
+            stat_list,
+            child: HashMap::new(),
+        }
+    }
+
+    /// UCB1 で player_id の action を選ぶ (まだ選んでいないものがあれば、先頭から順に選ぶ)
+    fn select(&self, player_id: usize) -> usize {
+        let stat_list = &self.stat_list[player_id];
+        if let Some(i) = stat_list.iter().position(|s| s.visit == 0) {
+            return i;
+        }
+        let log_visit = (self.visit as f64).ln();
+        let ucb = |s: &Stat| s.reward / s.visit as f64 + EXPLORATION * (log_visit / s.visit as f64).sqrt();
+        (0..stat_list.len())
+            .max_by(|&a, &b| ucb(&stat_list[a]).partial_cmp(&ucb(&stat_list[b])).unwrap())
+            .unwrap()
+    }
+}
+
+impl Mcts {
+    pub fn new() -> Mcts {
+        Mcts {
+            model: ForwardModel::new(SEED),
+        }
+    }
+
+    /// 根で一番多く選ばれた action の組
+    ///
+    /// hint は最初に試す組で、これが一番多く選ばれた時や、1回も回らなかった時は hint をそのまま返す
+    pub fn search(
+        &self,
+        board: &Board,
+        belief: &Belief,
+        hint: &[Action],
+        is_time_over: impl Fn() -> bool,
+    ) -> Vec<Action> {
+        let hint_command = forward::to_command_list(hint);
+        let root = self.model.determinize(board, belief, board.turn);
+        let base_score = beam::evaluate(&root);
+        let reward =
+            |state: &GameState| 1.0 / (1.0 + (-(beam::evaluate(state) - base_score) as f64 / REWARD_SCALE).exp());
+
+        let mut node_list = vec![Node::new(&root, 0, Some(&hint_command))];
+        let mut iteration = 0;
+        while iteration < MAX_ITERATION && !is_time_over() {
+            iteration += 1;
+
+            // 選択: 子 node が無い組に当たるまで降りる
+            let mut path = vec![];
+            let mut index = 0;
+            let mut state = root;
+            let value = loop {
+                let node = &node_list[index];
+                if node.depth == MAX_DEPTH || state.finish_game() {
+                    break reward(&state);
+                }
+                let choice = (node.select(0), node.select(1));
+                path.push((index, choice));
+                state.next_state(&[node.action_list[0][choice.0], node.action_list[1][choice.1]]);
+                if let Some(&child) = node.child.get(&choice) {
+                    index = child;
+                    continue;
+                }
+
+                // 展開して、簡単な方策で進めて評価する
+                let depth = node.depth + 1;
+                let child = node_list.len();
+                node_list[index].child.insert(choice, child);
+                node_list.push(Node::new(&state, depth, None));
+                for _ in 0..ROLLOUT_TURN {
+                    if state.finish_game() {
+                        break;
+                    }
+                    let command_list = [
+                        beam::default_command_list(&state, 0),
+                        beam::default_command_list(&state, 1),
+                    ];
+                    state.next_state(&command_list);
+                }
+                break reward(&state);
+            };
+
+            // 逆伝播
+            for (index, (a0, a1)) in path {
+                let node = &mut node_list[index];
+                node.visit += 1;
+                node.stat_list[0][a0].visit += 1;
+                node.stat_list[0][a0].reward += value;
+                node.stat_list[1][a1].visit += 1;
+                node.stat_list[1][a1].reward += 1.0 - value;
+            }
+        }
+
+        let root = &node_list[0];
+        let best = (0..root.action_list[0].len())
+            .max_by_key(|&i| (root.stat_list[0][i].visit, std::cmp::Reverse(i)))
+            .unwrap();
+        let command_list = root.action_list[0][best];
+        if root.stat_list[0][best].visit == 0 || command_list == hint_command {
+            return hint.to_vec();
+        }
+        command_list
+            .iter()
+            .map(|command| {
+                beam::with_message(
+                    command.to_action(),
+                    format!("[mcts]{}/{}", root.stat_list[0][best].visit, iteration),
+                )
+            })
+            .collect()
+    }
+}
+
+impl Default for Mcts {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use simulator::{MAX_X, MAX_Y};
+
+    #[test]
+    fn test_avoid_losing() {
+        let mut board = Board {
+            player: Player::new(),
+            opponent: Player::new(),
+            monster_list: vec![],
+            turn: 50,
+        };
+        board.player.health = 1;
+        board.player.mana = 50;
+        board.opponent.health = 3;
+        board.opponent.base = Point { x: MAX_X, y: MAX_Y };
+        // 次のターンに base に入る、倒しきれない monster
+        board.monster_list.push(Monster {
+            id: 10,
+            pos: Point { x: 400, y: 400 },
+            shield_life: 0,
+            is_controlled: false,
+            health: 20,
+            v: Point { x: -283, y: -283 },
+            threat_state: MonsterThreatState::PlayerThreat,
+        });
+        board.player.hero_list = (0..3)
+            .map(|id| Hero {
+                id,
+                pos: Point { x: 1000, y: 1000 },
+                shield_life: 0,
+                is_controlled: false,
+            })
+            .collect();
+
+        // 回数で打ち切る
+        let count = std::cell::Cell::new(0);
+        let is_time_over = || {
+            count.set(count.get() + 1);
+            count.get() > 3000
+        };
+        let hint = vec![Action::Wait { message: String::new() }; 3];
+        let action_list = Mcts::new().search(&board, &Belief::default(), &hint, is_time_over);
+        assert!(action_list.iter().any(|a| matches!(a, Action::Wind { .. })));
+    }
+}
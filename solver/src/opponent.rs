@@ -0,0 +1,383 @@
+//! 相手の戦略の推測
+//!
+//! 相手 hero の居場所、こちらの hero・monster にかけられた spell、相手のマナの減り方を数えて分類する
+
+use std::collections::VecDeque;
+
+use simulator::inout::*;
+use simulator::SHIELD_EFFECTIVE_TURN;
+
+use crate::params::Params;
+
+// 自陣からこの距離以内で見かけた相手 hero は、攻めに来ている
+const RUSH_RADIUS: i32 = 7000;
+// 相手陣からこの距離以内で見かけた相手 hero は、守っている
+const TURTLE_RADIUS: i32 = 6000;
+// これ以上飛ばされていたら wind を受けた (monster は 400, hero は 800 しか動けない)
+const PUSHED_DISTANCE: i32 = 1200;
+// 直近これだけのターンに、同じ種類の spell をこの回数見たら、その戦略とみなす
+// (昔の spell は忘れて、途中で戦略を変えてきても追いかける)
+const SPELL_WINDOW_TURN: usize = 30;
+const SPELL_COUNT_THRESHOLD: i32 = 3;
+// 序盤にこれだけ (hero * ターン) 自陣の近くで見かけたら、rusher とみなす
+const RUSH_COUNT_THRESHOLD: i32 = 10;
+const EARLY_TURN: usize = 80;
+// hero の居場所で分類するのは、これだけ様子を見てから
+const OBSERVE_TURN: usize = 40;
+// farmer はほとんどマナを使わない
+const FARMER_SPENT_MANA: i32 = 50;
+
+/// 前のターンから今のターンの間に、相手がかけたと思われる spell の回数
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct SpellObservation {
+    /// monster を自陣の方に飛ばした、もしくは自分の hero を飛ばした
+    pub wind: i32,
+    /// monster か自分の hero にかかっている control
+    pub control: i32,
+    /// monster にかけた shield
+    pub shield: i32,
+    /// 相手 hero が自分にかけた shield (こちらの陣に居座るために張る)
+    pub hero_shield: i32,
+}
+
+impl SpellObservation {
+    /// prev_action_list は前のターンに自分が出した action (自分の spell を相手のものと数えないため)
+    pub fn new(prev: &Board, board: &Board, prev_action_list: &[Action]) -> SpellObservation {
+        let mut ret = SpellObservation::default();
+        let used_wind = prev_action_list.iter().any(|a| matches!(a, Action::Wind { .. }));
+        let used_on = |id: i32| {
+            prev_action_list.iter().any(|a| match a {
+                Action::Shield { entity_id, .. } | Action::Control { entity_id, .. } => *entity_id == id,
+                _ => false,
+            })
+        };
+
+        for m in board.monster_list.iter() {
+            if m.shield_life == SHIELD_EFFECTIVE_TURN && !used_on(m.id) {
+                ret.shield += 1;
+            }
+            if m.is_controlled && !used_on(m.id) {
+                ret.control += 1;
+            }
+            // 自陣の方に飛ばされてきた
+            if let Some(prev_m) = prev.monster(m.id) {
+                if !used_wind
+                    && prev_m.next_pos().distance(&m.pos) >= PUSHED_DISTANCE
+                    && m.pos.distance(&board.player.base) < prev_m.pos.distance(&board.player.base)
+                {
+                    ret.wind += 1;
+                }
+            }
+        }
+
+        for (hero, prev_hero) in board.player.hero_list.iter().zip(prev.player.hero_list.iter()) {
+            if hero.is_controlled {
+                ret.control += 1;
+            } else if prev_hero.pos.distance(&hero.pos) >= PUSHED_DISTANCE {
+                ret.wind += 1;
+            }
+        }
+
+        for hero in board.opponent.hero_list.iter() {
+            if hero.shield_life == SHIELD_EFFECTIVE_TURN {
+                ret.hero_shield += 1;
+            }
+        }
+        ret
+    }
+
+    pub fn total(&self) -> i32 {
+        self.wind + self.control + self.shield + self.hero_shield
+    }
+
+    fn add(self, other: &SpellObservation) -> SpellObservation {
+        SpellObservation {
+            wind: self.wind + other.wind,
+            control: self.control + other.control,
+            shield: self.shield + other.shield,
+            hero_shield: self.hero_shield + other.hero_shield,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OpponentStrategyType {
+    NotEstimated,
+    /// 自陣に籠って守る (turtle)
+    CompletelyDefense,
+    /// 中央でマナを貯める
+    Farmer,
+    /// 序盤から hero を送り込んでくる
+    EarlyRusher,
+    /// wind で monster やこちらの hero を押し込んでくる
+    WindPusher,
+    /// control で monster をこちらに向けてくる
+    ControlRedirector,
+    /// monster に shield をかけて送り込んでくる
+    ShieldSpammer,
+}
+
+/// 分類に応じて変える、role の挙動のパラメータ
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PolicyParams {
+    /// 攻撃態勢に切り替えるマナ
+    pub attack_mana: i32,
+    /// 優先度の低い spell を撃つ時に、残しておく spell の回数
+    pub spell_reserve: i32,
+    /// defender が待機する、自陣からの距離
+    pub defender_distance: i32,
+}
+
+impl PolicyParams {
+    /// 分類できていない時は params.policy() のまま
+    pub fn new(strategy: OpponentStrategyType, params: &Params) -> PolicyParams {
+        let default = params.policy();
+        match strategy {
+            OpponentStrategyType::NotEstimated | OpponentStrategyType::Farmer => default,
+            // 籠られると崩しにくいので、早めにマナを溜めて攻める
+            OpponentStrategyType::CompletelyDefense => PolicyParams {
+                attack_mana: params.turtle_attack_mana,
+                ..default
+            },
+            OpponentStrategyType::EarlyRusher => PolicyParams {
+                attack_mana: params.rusher_attack_mana,
+                defender_distance: params.rusher_defender_distance,
+                ..default
+            },
+            // 押し込まれた monster を base の近くで拾えるように
+            OpponentStrategyType::WindPusher => PolicyParams {
+                spell_reserve: params.pusher_spell_reserve,
+                defender_distance: params.pusher_defender_distance,
+                ..default
+            },
+            OpponentStrategyType::ControlRedirector => PolicyParams {
+                spell_reserve: params.redirector_spell_reserve,
+                ..default
+            },
+            // shield 付きは wind で返せないので、外側で迎え撃って削る
+            OpponentStrategyType::ShieldSpammer => PolicyParams {
+                attack_mana: params.spammer_attack_mana,
+                defender_distance: params.spammer_defender_distance,
+                ..default
+            },
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct OpponentClassifier {
+    strategy: OpponentStrategyType,
+    // 分類に応じて変える前のパラメータ
+    params: Params,
+
+    // 相手 hero を見かけた回数 (hero * ターン)
+    rush_count: i32,
+    turtle_count: i32,
+    farm_count: i32,
+    // 直近 SPELL_WINDOW_TURN ターンの、相手がかけたと思われる spell (新しいものが後ろ)
+    spell_window: VecDeque<SpellObservation>,
+    // マナの減り方から分かる、相手が使ったマナの下限
+    spent_mana: i32,
+
+    prev_board: Option<Board>,
+    // 前のターンに自分が出した action (自分の spell を相手のものと数えないため)
+    prev_action_list: Vec<Action>,
+}
+
+impl OpponentClassifier {
+    pub fn new() -> OpponentClassifier {
+        Self::with_params(Params::default())
+    }
+
+    pub fn with_params(params: Params) -> OpponentClassifier {
+        OpponentClassifier {
+            strategy: OpponentStrategyType::NotEstimated,
+            params,
+            rush_count: 0,
+            turtle_count: 0,
+            farm_count: 0,
+            spell_window: VecDeque::new(),
+            spent_mana: 0,
+            prev_board: None,
+            prev_action_list: vec![],
+        }
+    }
+
+    pub fn strategy(&self) -> OpponentStrategyType {
+        self.strategy
+    }
+
+    pub fn params(&self) -> PolicyParams {
+        PolicyParams::new(self.strategy, &self.params)
+    }
+
+    /// 毎ターン、solve の最初に呼ぶ
+    pub fn update(&mut self, board: &Board) {
+        for hero in board.opponent.hero_list.iter() {
+            if hero.pos.distance(&board.player.base) <= RUSH_RADIUS {
+                self.rush_count += 1;
+            } else if hero.pos.distance(&board.opponent.base) <= TURTLE_RADIUS {
+                self.turtle_count += 1;
+            } else {
+                self.farm_count += 1;
+            }
+        }
+
+        if let Some(prev) = self.prev_board.take() {
+            if self.spell_window.len() >= SPELL_WINDOW_TURN {
+                self.spell_window.pop_front();
+            }
+            self.spell_window
+                .push_back(SpellObservation::new(&prev, board, &self.prev_action_list));
+            self.spent_mana += (prev.opponent.mana - board.opponent.mana).max(0);
+        }
+        self.prev_board = Some(board.clone());
+
+        self.strategy = self.classify(board.turn);
+    }
+
+    /// 今ターンに自分が出した action を覚えておく
+    pub fn record_action(&mut self, action_list: &[Action]) {
+        self.prev_action_list = action_list.to_vec();
+    }
+
+    fn classify(&self, turn: usize) -> OpponentStrategyType {
+        // 直近に spell の癖が見えたら、それを優先する
+        let spell = self
+            .spell_window
+            .iter()
+            .fold(SpellObservation::default(), |sum, s| sum.add(s));
+        let spell_list = [
+            (spell.shield, OpponentStrategyType::ShieldSpammer),
+            (spell.control, OpponentStrategyType::ControlRedirector),
+            (spell.wind, OpponentStrategyType::WindPusher),
+        ];
+        if let Some((_, strategy)) = spell_list
+            .iter()
+            .filter(|(count, _)| *count >= SPELL_COUNT_THRESHOLD)
+            .max_by_key(|(count, _)| *count)
+        {
+            return *strategy;
+        }
+
+        // 序盤を過ぎても、自分に shield を張って居座っているなら rusher のまま
+        if self.rush_count >= RUSH_COUNT_THRESHOLD && (turn <= EARLY_TURN || spell.hero_shield >= SPELL_COUNT_THRESHOLD)
+        {
+            OpponentStrategyType::EarlyRusher
+        } else if turn < OBSERVE_TURN {
+            self.strategy
+        } else if self.turtle_count > self.farm_count {
+            OpponentStrategyType::CompletelyDefense
+        } else if self.spent_mana <= FARMER_SPENT_MANA {
+            OpponentStrategyType::Farmer
+        } else {
+            self.strategy
+        }
+    }
+}
+
+impl Default for OpponentClassifier {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::params::DEFAULT_PARAMS;
+    use simulator::{MAX_X, MAX_Y};
+
+    fn hero(id: i32, pos: Point) -> Hero {
+        Hero {
+            id,
+            pos,
+            shield_life: 0,
+            is_controlled: false,
+        }
+    }
+
+    fn board(turn: usize, monster_list: Vec<Monster>) -> Board {
+        let mut board = Board {
+            player: Player::new(),
+            opponent: Player::new(),
+            monster_list,
+            turn,
+        };
+        board.opponent.base = Point { x: MAX_X, y: MAX_Y };
+        board.player.hero_list = (0..3).map(|id| hero(id, Point { x: 3000, y: 3000 })).collect();
+        board
+    }
+
+    #[test]
+    fn test_shield_spammer() {
+        let mut classifier = OpponentClassifier::new();
+        for turn in 0..10 {
+            let monster = Monster {
+                id: 10 + turn as i32,
+                pos: Point { x: 4000, y: 2000 },
+                shield_life: SHIELD_EFFECTIVE_TURN,
+                is_controlled: false,
+                health: 10,
+                v: Point { x: -400, y: 0 },
+                threat_state: MonsterThreatState::PlayerThreat,
+            };
+            classifier.update(&board(turn, vec![monster]));
+        }
+        assert_eq!(classifier.strategy(), OpponentStrategyType::ShieldSpammer);
+        assert_eq!(
+            classifier.params().defender_distance,
+            DEFAULT_PARAMS.spammer_defender_distance
+        );
+
+        // 自分でかけた shield は数えない
+        let mut classifier = OpponentClassifier::new();
+        for turn in 0..10 {
+            let monster = Monster {
+                id: 10,
+                pos: Point { x: 4000, y: 2000 },
+                shield_life: SHIELD_EFFECTIVE_TURN,
+                is_controlled: false,
+                health: 10,
+                v: Point { x: -400, y: 0 },
+                threat_state: MonsterThreatState::PlayerThreat,
+            };
+            classifier.update(&board(turn, vec![monster]));
+            classifier.record_action(&[Action::Shield {
+                entity_id: 10,
+                message: String::new(),
+            }]);
+        }
+        assert_eq!(classifier.strategy(), OpponentStrategyType::NotEstimated);
+    }
+
+    #[test]
+    fn test_switch_strategy() {
+        let monster = |id: i32, shield_life: i32, is_controlled: bool| Monster {
+            id,
+            pos: Point { x: 4000, y: 2000 },
+            shield_life,
+            is_controlled,
+            health: 10,
+            v: Point { x: -400, y: 0 },
+            threat_state: MonsterThreatState::PlayerThreat,
+        };
+        let mut classifier = OpponentClassifier::new();
+        for turn in 0..10 {
+            classifier.update(&board(
+                turn,
+                vec![monster(10 + turn as i32, SHIELD_EFFECTIVE_TURN, false)],
+            ));
+        }
+        assert_eq!(classifier.strategy(), OpponentStrategyType::ShieldSpammer);
+
+        // shield を見なくなってから control を使い始めたら、分類を変える
+        for turn in 10..50 {
+            classifier.update(&board(turn, vec![]));
+        }
+        for turn in 50..55 {
+            classifier.update(&board(turn, vec![monster(100 + turn as i32, 0, true)]));
+        }
+        assert_eq!(classifier.strategy(), OpponentStrategyType::ControlRedirector);
+    }
+}
@@ -0,0 +1,272 @@
+//! solver の調整できるパラメータ
+//!
+//! 既定値は DEFAULT_PARAMS で、`config` feature を有効にすると TOML / JSON のファイルや環境変数から読める
+//! 提出用の submit は外部の crate もファイルも使えないので、bake で Rust の定数として書き出して埋め込む
+
+use crate::opponent::PolicyParams;
+
+macro_rules! define_params {
+    ($($(#[doc = $doc:expr])* $name:ident: $default:expr,)*) => {
+        #[derive(Clone, Copy, Debug, PartialEq)]
+        #[cfg_attr(feature = "config", derive(serde::Serialize, serde::Deserialize), serde(default, deny_unknown_fields))]
+        pub struct Params {
+            $($(#[doc = $doc])* pub $name: i32,)*
+        }
+
+        pub const DEFAULT_PARAMS: Params = Params {
+            $($name: $default,)*
+        };
+
+        impl Params {
+            /// (名前, 値) を宣言順に
+            pub fn field_list(&self) -> Vec<(&'static str, i32)> {
+                vec![$((stringify!($name), self.$name),)*]
+            }
+
+            pub fn field_mut(&mut self, name: &str) -> Option<&mut i32> {
+                match name {
+                    $(stringify!($name) => Some(&mut self.$name),)*
+                    _ => None,
+                }
+            }
+        }
+    };
+}
+
+define_params! {
+    /// マナ集めの hero 0 の home (盤面の対角線上の位置 [%])
+    farmer0_home_percent: 60,
+    /// マナ集めの hero 1 の home (盤面の対角線上の位置 [%])
+    farmer1_home_percent: 40,
+    /// マナ集めの hero 0, 1 は、自陣からこれより遠くでしか殴らない
+    farm_distance: 8000,
+    /// 攻撃態勢に切り替えるマナ (相手の戦略で変わる前の値)
+    attack_mana: 200,
+    /// 相手よりこれだけマナが多ければ、attack_mana に届かなくても攻撃態勢に切り替える
+    mana_lead_to_attack: 100,
+    /// 優先度の低い spell を撃つ時に、残しておく spell の回数 (相手の戦略で変わる前の値)
+    spell_reserve: 2,
+    /// defender が待機する、自陣からの距離 (相手の戦略で変わる前の値)
+    ///
+    /// 1体で守る時は対角線上の (3000, 3000) で待つ
+    defender_distance: 4243,
+    /// 相手が自陣に籠っている時の attack_mana
+    turtle_attack_mana: 150,
+    /// 相手が早くから攻めてくる時の attack_mana, defender_distance
+    rusher_attack_mana: 250,
+    rusher_defender_distance: 3000,
+    /// 相手が wind で押し込んでくる時の spell_reserve, defender_distance
+    pusher_spell_reserve: 3,
+    pusher_defender_distance: 2500,
+    /// 相手が control で monster をこちらに向けてくる時の spell_reserve
+    redirector_spell_reserve: 3,
+    /// 相手が shield 付きの monster を送り込んでくる時の attack_mana, defender_distance
+    spammer_attack_mana: 250,
+    spammer_defender_distance: 5000,
+    /// 自分の hero が 1ターンでこれ以上動かされていたら、相手に wind を使われた
+    wind_detect_distance: 1200,
+    /// attacker の home の、相手陣の角からの距離 (x, y それぞれ)
+    attacker_home_offset: 2000,
+    /// attacker が home にこのターン数居座ったら、home を手前にずらす
+    attacker_idle_turn: 10,
+    attacker_home_shift: 1000,
+    /// midfielder の home の、相手陣の角からの距離 (x, y それぞれ)
+    midfielder_home_offset: 5500,
+    /// attacker, midfielder は、home からこれより離れたら戻る
+    front_return_distance: 4000,
+    /// attacker は、成功率がこれ以上 [%] の時だけ wind で monster を押し込みに行く
+    wind_chain_success_percent: 60,
+    /// 攻撃態勢で、これだけマナがあれば 2体揃って押し込む (半分を切るまで続ける)
+    pair_attack_mana: 300,
+}
+
+impl Default for Params {
+    fn default() -> Self {
+        DEFAULT_PARAMS
+    }
+}
+
+impl Params {
+    /// 相手の戦略で変わる前の、role の挙動のパラメータ
+    pub fn policy(&self) -> PolicyParams {
+        PolicyParams {
+            attack_mana: self.attack_mana,
+            spell_reserve: self.spell_reserve,
+            defender_distance: self.defender_distance,
+        }
+    }
+
+    /// submit に埋め込む Rust のコード (SUBMIT_FIELD_LIST のものだけ)
+    ///
+    /// submit/src/main.rs の BAKE_BEGIN から BAKE_END までをこれで置き換える
+    pub fn bake(&self) -> String {
+        let field_list = self
+            .field_list()
+            .into_iter()
+            .filter(|(name, _)| SUBMIT_FIELD_LIST.contains(name))
+            .collect::<Vec<_>>();
+        let mut ret = vec![
+            BAKE_BEGIN.to_string(),
+            "#[derive(Clone, Copy, Debug)]".to_string(),
+            "struct Params {".to_string(),
+        ];
+        for (name, _) in field_list.iter() {
+            ret.push(format!("    {}: i32,", name));
+        }
+        ret.push("}".to_string());
+        ret.push(String::new());
+        ret.push("const PARAMS: Params = Params {".to_string());
+        for (name, value) in field_list.iter() {
+            ret.push(format!("    {}: {},", name, value));
+        }
+        ret.push("};".to_string());
+        ret.push(BAKE_END.to_string());
+        ret.join("\n") + "\n"
+    }
+
+    /// from_toml で読める形
+    pub fn to_toml(&self) -> String {
+        self.field_list()
+            .iter()
+            .map(|(name, value)| format!("{} = {}\n", name, value))
+            .collect()
+    }
+
+    /// `名前 = 値` の行だけの TOML (`#` からはコメント)
+    ///
+    /// 書かれていないものは既定値のまま
+    pub fn from_toml(text: &str) -> Result<Params, String> {
+        let mut ret = Params::default();
+        for (line_number, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let (name, value) = line
+                .split_once('=')
+                .ok_or(format!("line {}: expected `name = value`", line_number + 1))?;
+            ret.set(name.trim(), value.trim())?;
+        }
+        Ok(ret)
+    }
+
+    fn set(&mut self, name: &str, value: &str) -> Result<(), String> {
+        let field = self.field_mut(name).ok_or(format!("unknown parameter `{}`", name))?;
+        *field = value
+            .parse()
+            .map_err(|_| format!("invalid value for `{}`: `{}`", name, value))?;
+        Ok(())
+    }
+}
+
+/// submit が読むパラメータ
+///
+/// submit は solver より前の作りで、role の組み方が違うので、同じ意味で使えるものだけを埋め込む
+pub const SUBMIT_FIELD_LIST: [&str; 4] = [
+    "attack_mana",
+    "spell_reserve",
+    "defender_distance",
+    "wind_detect_distance",
+];
+
+pub const BAKE_BEGIN: &str = "// BAKE_BEGIN: solver の bake_params で生成 (手で書き換えない)";
+pub const BAKE_END: &str = "// BAKE_END";
+
+/// source の BAKE_BEGIN の行から BAKE_END の行までを、baked に置き換える
+pub fn replace_baked(source: &str, baked: &str) -> Result<String, String> {
+    let begin = source.find(BAKE_BEGIN).ok_or("BAKE_BEGIN not found")?;
+    let end = source[begin..].find(BAKE_END).ok_or("BAKE_END not found")? + begin;
+    let end = source[end..].find('\n').map_or(source.len(), |i| end + i + 1);
+    Ok(format!("{}{}{}", &source[..begin], baked, &source[end..]))
+}
+
+#[cfg(feature = "config")]
+mod config {
+    use super::Params;
+
+    /// ファイルの場所を指す環境変数
+    pub const PARAMS_ENV: &str = "SOLVER_PARAMS";
+    /// SOLVER_ATTACK_MANA=150 のように、1つずつ上書きする環境変数の接頭辞
+    pub const PARAM_ENV_PREFIX: &str = "SOLVER_";
+
+    impl Params {
+        pub fn from_json(text: &str) -> Result<Params, String> {
+            serde_json::from_str(text).map_err(|e| e.to_string())
+        }
+
+        /// 拡張子が .json なら JSON、それ以外は TOML として読む
+        pub fn load(path: &str) -> Result<Params, String> {
+            let text = std::fs::read_to_string(path).map_err(|e| format!("failed to open {}: {}", path, e))?;
+            if path.ends_with(".json") {
+                Params::from_json(&text)
+            } else {
+                Params::from_toml(&text)
+            }
+        }
+
+        /// SOLVER_PARAMS のファイルを読んでから、SOLVER_<名前> で1つずつ上書きする
+        pub fn from_env() -> Result<Params, String> {
+            let mut ret = match std::env::var(PARAMS_ENV) {
+                Ok(path) => Params::load(&path)?,
+                Err(_) => Params::default(),
+            };
+            for (name, _) in Params::default().field_list() {
+                let key = format!("{}{}", PARAM_ENV_PREFIX, name.to_uppercase());
+                if let Ok(value) = std::env::var(&key) {
+                    ret.set(name, &value)?;
+                }
+            }
+            Ok(ret)
+        }
+    }
+}
+
+#[cfg(feature = "config")]
+pub use config::{PARAMS_ENV, PARAM_ENV_PREFIX};
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_from_toml() {
+        let params =
+            Params::from_toml("# 早めに攻める\nattack_mana = 150\n\nfarm_distance = 7000 # 少し手前\n").unwrap();
+        assert_eq!(params.attack_mana, 150);
+        assert_eq!(params.farm_distance, 7000);
+        assert_eq!(params.spell_reserve, DEFAULT_PARAMS.spell_reserve);
+
+        assert_eq!(Params::from_toml(&params.to_toml()).unwrap(), params);
+        assert!(Params::from_toml("attack = 150").is_err());
+        assert!(Params::from_toml("attack_mana = many").is_err());
+
+        // bake した定数は、submit が読む値だけを持っている
+        let baked = params.bake();
+        assert!(baked.starts_with(BAKE_BEGIN));
+        assert!(baked.contains("    attack_mana: 150,\n"));
+        assert!(baked.contains("    wind_detect_distance: i32,\n"));
+        assert!(!baked.contains("farm_distance"));
+
+        let source = format!("fn main() {{}}\n{}const X: i32 = 1;\n", DEFAULT_PARAMS.bake());
+        assert_eq!(
+            replace_baked(&source, &baked).unwrap(),
+            format!("fn main() {{}}\n{}const X: i32 = 1;\n", baked)
+        );
+    }
+
+    #[test]
+    fn test_submit_params() {
+        // submit に埋め込んだ値は、params.toml から作り直したものと一致する
+        let params = Params::from_toml(include_str!("../../submit/params.toml")).unwrap();
+        assert!(include_str!("../../submit/src/main.rs").contains(&params.bake()));
+
+        // 埋め込む前に submit が使っていた定数と同じ動きになる
+        assert_eq!(params.attack_mana, 150);
+        assert_eq!(params.spell_reserve, 2);
+        assert_eq!(
+            (params.defender_distance as f64 / std::f64::consts::SQRT_2) as i32,
+            3000
+        );
+        assert_eq!(params.wind_detect_distance, simulator::WIND_EFFECTIVE_RADIUS - 100);
+    }
+}
@@ -0,0 +1,253 @@
+//! マナの割り当て
+//!
+//! 1回目は全員が spell を撃てるものとして action を決めて、spell の要求を集める
+//! 緊急の要求から順にマナを割り当て、それ以外は数ターン先の防衛に必要な分を残して割り当てる
+//! 2回目は割り当てに従って action を決め直す
+
+use simulator::inout::*;
+use simulator::{BASE_ATTACK_RADIUS, MANA_TO_SPELL, MAX_MONSTER_VELOCITY};
+
+use crate::tracker::Tracker;
+
+// 何ターン先までの防衛を見越してマナを残すか
+const PLAN_TURN: i32 = 5;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Urgency {
+    Normal,
+    /// 撃たないと base にダメージを受けるような spell
+    Emergency,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct SpellRequest {
+    pub hero_id: usize,
+    pub urgency: Urgency,
+    /// 同じ urgency の中での優先度 (自陣に近い hero の spell ほど大きい)
+    pub value: i32,
+}
+
+#[derive(Clone, Debug)]
+enum Mode {
+    /// 要求を集める
+    Collect(Vec<Option<SpellRequest>>),
+    /// hero 毎に、spell を撃って良いか
+    Granted(Vec<bool>),
+}
+
+#[derive(Clone, Debug)]
+pub struct ManaPlanner {
+    mode: Mode,
+    // 今 action を決めている hero
+    current_hero: usize,
+    // 直前の allocate で残したマナと、集まった要求
+    reserved_mana: i32,
+    request_list: Vec<SpellRequest>,
+}
+
+impl ManaPlanner {
+    pub fn new() -> ManaPlanner {
+        ManaPlanner {
+            mode: Mode::Granted(vec![]),
+            current_hero: 0,
+            reserved_mana: 0,
+            request_list: vec![],
+        }
+    }
+
+    pub fn begin_collect(&mut self, hero_size: usize) {
+        self.mode = Mode::Collect(vec![None; hero_size]);
+    }
+
+    pub fn set_current_hero(&mut self, hero_id: usize) {
+        self.current_hero = hero_id;
+    }
+
+    /// 今の hero が spell を撃って良いか
+    ///
+    /// 要求を集めている間は、マナが足りる限り撃てるものとして要求を覚えておく
+    pub fn request(&mut self, board: &Board, urgency: Urgency) -> bool {
+        let hero_id = self.current_hero;
+        match &mut self.mode {
+            Mode::Collect(request_list) => {
+                let hero = &board.player.hero_list[hero_id];
+                request_list[hero_id] = Some(SpellRequest {
+                    hero_id,
+                    urgency,
+                    value: -hero.pos.distance(&board.player.base),
+                });
+                board.player.mana >= MANA_TO_SPELL
+            }
+            Mode::Granted(granted) => granted.get(hero_id).copied().unwrap_or(false),
+        }
+    }
+
+    /// allocate で hero_id の spell にマナを割り当てたか
+    pub fn granted(&self, hero_id: usize) -> bool {
+        match &self.mode {
+            Mode::Granted(granted) => granted.get(hero_id).copied().unwrap_or(false),
+            Mode::Collect(_) => false,
+        }
+    }
+
+    /// 集めた要求のうち、実際に spell になったものにマナを割り当てる
+    ///
+    /// reserve_spell は、緊急でない spell を撃つ時に残しておく spell の回数
+    pub fn allocate(&mut self, board: &Board, action_list: &[Action], reserve_spell: i32) {
+        let mut request_list = match &self.mode {
+            Mode::Collect(request_list) => request_list
+                .iter()
+                .zip(action_list.iter())
+                .filter(|(_, action)| is_spell(action))
+                .filter_map(|(request, _)| *request)
+                .collect::<Vec<_>>(),
+            Mode::Granted(_) => vec![],
+        };
+        request_list.sort_by_key(|r| std::cmp::Reverse((r.urgency, r.value)));
+
+        let mut mana = board.player.mana;
+        let mut granted = vec![false; action_list.len()];
+        for request in request_list.iter() {
+            let reserve = match request.urgency {
+                Urgency::Emergency => 0,
+                Urgency::Normal => reserve_spell * MANA_TO_SPELL,
+            };
+            if mana - reserve >= MANA_TO_SPELL {
+                mana -= MANA_TO_SPELL;
+                granted[request.hero_id] = true;
+            }
+        }
+
+        self.reserved_mana = mana;
+        self.request_list = request_list;
+        self.mode = Mode::Granted(granted);
+    }
+
+    pub fn describe(&self) -> Vec<String> {
+        let mut ret = vec![format!("reserved mana: {}", self.reserved_mana)];
+        let granted = match &self.mode {
+            Mode::Granted(granted) => granted.clone(),
+            Mode::Collect(_) => vec![],
+        };
+        for r in self.request_list.iter() {
+            ret.push(format!(
+                "  spell request: h{} {:?} {} {}",
+                r.hero_id,
+                r.urgency,
+                r.value,
+                if granted.get(r.hero_id) == Some(&true) {
+                    "granted"
+                } else {
+                    "denied"
+                }
+            ));
+        }
+        ret
+    }
+}
+
+impl Default for ManaPlanner {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// PLAN_TURN 以内に base に届きそうな monster の数 (それぞれ wind 1回分の備えが要る)
+pub fn emergency_count(board: &Board, tracker: &Tracker) -> i32 {
+    board
+        .monster_list
+        .iter()
+        .chain(tracker.unseen_monster_list(board).map(|t| &t.monster))
+        .filter(|m| m.threat_state.threat_player())
+        .filter(|m| {
+            let distance = m.pos.distance(&board.player.base) - BASE_ATTACK_RADIUS;
+            distance <= PLAN_TURN * MAX_MONSTER_VELOCITY
+        })
+        .count() as i32
+}
+
+fn is_spell(action: &Action) -> bool {
+    matches!(
+        action,
+        Action::Wind { .. } | Action::Shield { .. } | Action::Control { .. }
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_emergency_first() {
+        let mut board = Board {
+            player: Player::new(),
+            opponent: Player::new(),
+            monster_list: vec![],
+            turn: 10,
+        };
+        board.player.mana = 10;
+        board.player.hero_list = [8000, 6000, 1000]
+            .iter()
+            .map(|&x| Hero {
+                id: 0,
+                pos: Point { x, y: 0 },
+                shield_life: 0,
+                is_controlled: false,
+            })
+            .collect();
+
+        let mut planner = ManaPlanner::new();
+        planner.begin_collect(3);
+        for (hero_id, urgency) in [Urgency::Normal, Urgency::Normal, Urgency::Emergency]
+            .into_iter()
+            .enumerate()
+        {
+            planner.set_current_hero(hero_id);
+            assert!(planner.request(&board, urgency));
+        }
+        let control = Action::Control {
+            entity_id: 10,
+            point: Point { x: 0, y: 0 },
+            message: String::new(),
+        };
+        let wind = Action::Wind {
+            point: Point { x: 0, y: 0 },
+            message: String::new(),
+        };
+        planner.allocate(&board, &[control.clone(), control, wind], 0);
+
+        // 先に要求した attacker ではなく、defender の wind にマナを回す
+        let granted = (0..3)
+            .map(|hero_id| {
+                planner.set_current_hero(hero_id);
+                planner.request(&board, Urgency::Normal)
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(granted, vec![false, false, true]);
+    }
+
+    #[test]
+    fn test_emergency_count() {
+        let monster = |id: i32, x: i32, threat_state: MonsterThreatState| Monster {
+            id,
+            pos: Point { x, y: 0 },
+            shield_life: 0,
+            is_controlled: false,
+            health: 10,
+            v: Point { x: -400, y: 0 },
+            threat_state,
+        };
+        let board = Board {
+            player: Player::new(),
+            opponent: Player::new(),
+            monster_list: vec![
+                monster(10, 1000, MonsterThreatState::PlayerThreat),
+                monster(11, 1500, MonsterThreatState::PlayerThreatInTheFuture),
+                // 相手の base に向かう monster は、こちらの備えに数えない
+                monster(12, 1200, MonsterThreatState::OpponentThreat),
+            ],
+            turn: 10,
+        };
+        assert_eq!(emergency_count(&board, &Tracker::default()), 2);
+    }
+}
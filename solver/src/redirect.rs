@@ -0,0 +1,222 @@
+//! CONTROL で monster の向きを変えて、相手陣に送る計画
+//!
+//! control された monster は、そのターンに指定した点に向かって 400 動き、以降もその向きに直進する
+//! 相手の base の 5000 以内に入れば base に向かうので、そこに一番早く入る向きを探す
+//! 真っ直ぐ base に向けるのが一番早いが、途中に相手 hero がいると殴られるので、
+//! 向きを少しずつ変えた候補の中から、相手 hero の近くを通らない道を選ぶ
+//!
+//! wind が届くなら、2200 押して元の速度で進ませる方が早く入ることもある
+//! control は同じターンに wind されると無駄になるので、その恐れがある時も wind を選ぶ
+
+use simulator::inout::*;
+use simulator::{
+    FPoint, BASE_ATTRACTION_RADIUS, CONTROL_EFFECTIVE_RADIUS, HERO_ATTACK_RADIUS, MANA_GAIN_TO_ATTACK, MAP_LIMIT,
+    MAX_HERO_VELOCITY, MAX_MONSTER_VELOCITY, MAX_X, MAX_Y, WIND_DISTANCE, WIND_EFFECTIVE_RADIUS,
+};
+
+use crate::geometry::HORIZON;
+
+// base に向かう向きから左右にずらす角度の刻みと最大 [度]
+const ANGLE_STEP: i32 = 10;
+const MAX_ANGLE: i32 = 90;
+// 入るまでに相手 hero に 1回殴られるのを、何ターン遅れるのと同じとみなすか
+const HIT_TURN: usize = 2;
+// control が同じターンの wind で無駄になりそうな時に、何ターン遅れるのと同じとみなすか
+const WASTE_TURN: usize = 4;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Redirect {
+    Control { point: Point },
+    Wind { point: Point },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RedirectPlan {
+    pub monster_id: i32,
+    pub spell: Redirect,
+    /// 相手の base の 5000 以内に入るターン
+    pub entry: usize,
+    /// 入るまでに相手 hero に殴られる回数
+    pub hit: usize,
+    cost: usize,
+}
+
+impl RedirectPlan {
+    pub fn action(&self, message: String) -> Action {
+        match self.spell {
+            Redirect::Control { point } => Action::Control {
+                entity_id: self.monster_id,
+                point,
+                message,
+            },
+            Redirect::Wind { point } => Action::Wind { point, message },
+        }
+    }
+}
+
+/// hero から spell で m を相手陣に送る一番良い計画
+///
+/// 届かない時と、spell を使わなくても同じくらい早く入る時は None
+pub fn plan(board: &Board, m: &Monster, hero: &Point) -> Option<RedirectPlan> {
+    if m.shield_life > 0 || !hero.in_range(&m.pos, CONTROL_EFFECTIVE_RADIUS) {
+        return None;
+    }
+    let base = board.opponent.base;
+    let natural = entry_turn(&base, m.pos, m.v);
+
+    // 相手 hero がこのターンに wind を撃てる所にいる
+    let may_be_winded = board
+        .opponent
+        .hero_list
+        .iter()
+        .any(|h| h.pos.in_range(&m.pos, MAX_HERO_VELOCITY + WIND_EFFECTIVE_RADIUS));
+
+    let to_base = (base - m.pos).to_f64().normalize();
+    let mut candidate_list = (-MAX_ANGLE / ANGLE_STEP..=MAX_ANGLE / ANGLE_STEP)
+        .filter_map(|i| {
+            let dir = rotate(&to_base, i * ANGLE_STEP);
+            let v = (dir * MAX_MONSTER_VELOCITY as f64).to::<i32>();
+            let (entry, hit) = evaluate(board, m, m.pos + v, v)?;
+            let waste = if may_be_winded { WASTE_TURN } else { 0 };
+            Some(RedirectPlan {
+                monster_id: m.id,
+                spell: Redirect::Control {
+                    point: m.pos + (dir * CONTROL_EFFECTIVE_RADIUS as f64).to::<i32>(),
+                },
+                entry,
+                hit,
+                cost: entry + HIT_TURN * hit + waste,
+            })
+        })
+        .collect::<Vec<_>>();
+
+    if hero.in_range(&m.pos, WIND_EFFECTIVE_RADIUS) {
+        // monster から base に向かう向きに押すと、押された後は元の速度で進む
+        let pushed = m.pos + (to_base * WIND_DISTANCE as f64).to::<i32>();
+        let v = if base.in_range(&pushed, BASE_ATTRACTION_RADIUS) {
+            (base - pushed).to_f64().normalize() * MAX_MONSTER_VELOCITY as f64
+        } else {
+            m.v.to_f64()
+        };
+        if let Some((entry, hit)) = evaluate(board, m, pushed, v.to::<i32>()) {
+            candidate_list.push(RedirectPlan {
+                monster_id: m.id,
+                spell: Redirect::Wind {
+                    point: *hero + (base - m.pos),
+                },
+                entry,
+                hit,
+                cost: entry + HIT_TURN * hit,
+            });
+        }
+    }
+
+    // 同じ cost なら wind (後に積んだ方) を選ぶ
+    candidate_list
+        .into_iter()
+        .rev()
+        .min_by_key(|p| p.cost)
+        .filter(|p| natural.is_none_or(|natural| p.entry < natural))
+}
+
+/// pos から v で直進した時に base の 5000 以内に入るターン (spell を撃ったターンを 1 とする)
+fn entry_turn(base: &Point, mut pos: Point, v: Point) -> Option<usize> {
+    (1..=HORIZON).find(|_| {
+        pos = pos + v;
+        base.in_range(&pos, BASE_ATTRACTION_RADIUS)
+    })
+}
+
+/// このターンの終わりに pos にいて、以降 v で直進する m が入るターンと、それまでに相手 hero に殴られる回数
+///
+/// 入る前に盤外に出てしまう時と、殴り倒されてしまう時は None
+fn evaluate(board: &Board, m: &Monster, pos: Point, v: Point) -> Option<(usize, usize)> {
+    let base = board.opponent.base;
+    let mut path = vec![pos];
+    while !base.in_range(path.last().unwrap(), BASE_ATTRACTION_RADIUS) {
+        if path.len() > HORIZON {
+            return None;
+        }
+        path.push(*path.last().unwrap() + v);
+    }
+    let inside = |p: &Point| -MAP_LIMIT < p.x && p.x < MAX_X + MAP_LIMIT && -MAP_LIMIT < p.y && p.y < MAX_Y + MAP_LIMIT;
+    if !path.iter().all(inside) {
+        return None;
+    }
+    // 相手 hero は持ち場を大きく離れないものとして、1ターンで殴りに来られる所を通る度に 1回殴られる
+    let hit = board
+        .opponent
+        .hero_list
+        .iter()
+        .map(|h| {
+            path[..path.len() - 1]
+                .iter()
+                .filter(|p| h.pos.in_range(p, MAX_HERO_VELOCITY + HERO_ATTACK_RADIUS))
+                .count()
+        })
+        .sum::<usize>();
+    if MANA_GAIN_TO_ATTACK * hit as i32 >= m.health {
+        return None;
+    }
+    Some((path.len(), hit))
+}
+
+/// v を degree [度] 回す
+fn rotate(v: &FPoint, degree: i32) -> FPoint {
+    let (s, c) = (degree as f64).to_radians().sin_cos();
+    FPoint {
+        x: c * v.x - s * v.y,
+        y: s * v.x + c * v.y,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_plan() {
+        let mut board = Board {
+            player: Player::new(),
+            opponent: Player::new(),
+            monster_list: vec![],
+            turn: 100,
+        };
+        let base = Point { x: MAX_X, y: MAX_Y };
+        board.opponent.base = base;
+        // 相手陣の横を素通りする monster
+        let m = Monster {
+            id: 10,
+            pos: Point { x: 9000, y: 4000 },
+            shield_life: 0,
+            is_controlled: false,
+            health: 20,
+            v: Point { x: 400, y: 0 },
+            threat_state: MonsterThreatState::NotThreat,
+        };
+
+        // wind の届かない所からは、base に真っ直ぐ向ける
+        let far = plan(&board, &m, &Point { x: 9000, y: 2000 }).unwrap();
+        assert!(matches!(far.spell, Redirect::Control { .. }));
+        assert_eq!(far.hit, 0);
+        let direct = entry_turn(&base, m.pos, ((base - m.pos).to_f64().normalize() * 400.0).to::<i32>());
+        assert_eq!(Some(far.entry), direct);
+
+        // 真っ直ぐの道を相手 hero が塞いでいると、少し逸らす
+        board.opponent.hero_list.push(Hero {
+            id: 3,
+            pos: Point { x: 12500, y: 6500 },
+            shield_life: 0,
+            is_controlled: false,
+        });
+        let detour = plan(&board, &m, &Point { x: 9000, y: 2000 }).unwrap();
+        assert!(detour.entry > far.entry);
+        assert_eq!(detour.hit, 0);
+        board.opponent.hero_list.clear();
+
+        // wind が届けば、押した方が早い
+        let near = plan(&board, &m, &Point { x: 8500, y: 4000 }).unwrap();
+        assert!(matches!(near.spell, Redirect::Wind { .. }));
+        assert!(near.entry < far.entry);
+    }
+}
@@ -0,0 +1,235 @@
+//! hero への役割の割り当て
+//!
+//! 毎ターン、欲しい役割の組 (Formation) から持ち場 (Slot) を並べ、
+//! 持ち場までのターン数の和が一番小さくなるように hero を割り当て直す
+//! 割り当てがころころ変わると持ち場の間を行き来するだけになるので、今の役割から変える hero には
+//! SWITCH_TURN ターン分、monster を追っている最中の hero には更に BUSY_TURN ターン分の cost を上乗せする
+//!
+//! PairAttack の hero は 1つの組 (RoleGroup) として、揃って wind を撃つ
+
+use simulator::inout::*;
+use simulator::MAX_HERO_VELOCITY;
+
+use crate::endgame::EndgamePlan;
+
+// 役割を変える時に上乗せする cost [ターン]
+const SWITCH_TURN: i32 = 3;
+// monster を追っている最中の hero の役割を変える時に、更に上乗せする cost [ターン]
+const BUSY_TURN: i32 = 10;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Role {
+    Defender,
+    Farmer,
+    MidFielder,
+    Attacker,
+    /// 2体で揃って wind を撃って押し込む
+    PairAttack,
+}
+
+/// 欲しい役割の組
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Formation {
+    /// 1体で守り、2体でマナを集める
+    Farm,
+    /// 守り、midfielder, attacker を 1体ずつ
+    Attack,
+    /// 1体で守り、2体で押し込む
+    PairAttack,
+    /// sudden death に入ってからの方針
+    Endgame(EndgamePlan),
+}
+
+impl Formation {
+    pub fn role_list(&self) -> [Role; 3] {
+        match self {
+            Formation::Farm | Formation::Endgame(EndgamePlan::Farm) => [Role::Farmer, Role::Farmer, Role::Defender],
+            Formation::Attack => [Role::Attacker, Role::MidFielder, Role::Defender],
+            Formation::PairAttack => [Role::PairAttack, Role::PairAttack, Role::Defender],
+            Formation::Endgame(EndgamePlan::Defend) => [Role::Defender; 3],
+            Formation::Endgame(EndgamePlan::Attack { all_in }) => [
+                Role::PairAttack,
+                Role::PairAttack,
+                if *all_in { Role::Attacker } else { Role::Defender },
+            ],
+        }
+    }
+}
+
+/// 役割と持ち場
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Slot {
+    pub role: Role,
+    /// 同じ役割の中で何番目か (farmer の home などを分ける)
+    pub index: usize,
+    pub point: Point,
+}
+
+impl Slot {
+    /// 持ち場の位置が変わっても、同じ役割の同じ番なら同じ slot
+    pub fn is_same(&self, other: &Slot) -> bool {
+        self.role == other.role && self.index == other.index
+    }
+}
+
+/// 同じ役割で一緒に動く hero の組 (PairAttack 以外は 1体ずつ)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RoleGroup {
+    pub role: Role,
+    pub hero_list: Vec<usize>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct RoleManager {
+    // hero 毎の今の役割
+    slot_list: Vec<Slot>,
+}
+
+impl RoleManager {
+    /// slot_list を hero に 1つずつ割り当てて、hero 毎の slot を返す
+    ///
+    /// hero_list は hero の位置、busy は monster を追っている最中の hero
+    /// まだ役割の無い最初のターンは、slot_list の順に割り当てる
+    pub fn assign(&mut self, hero_list: &[Point], slot_list: &[Slot], busy: &[bool]) -> Vec<Slot> {
+        assert_eq!(hero_list.len(), slot_list.len());
+        if self.slot_list.is_empty() {
+            self.slot_list = slot_list.to_vec();
+            return slot_list.to_vec();
+        }
+        let cost = |hero_id: usize, slot: &Slot| {
+            let turn = hero_list[hero_id].distance(&slot.point) / MAX_HERO_VELOCITY;
+            if self.slot_list[hero_id].is_same(slot) {
+                turn
+            } else if busy[hero_id] {
+                turn + SWITCH_TURN + BUSY_TURN
+            } else {
+                turn + SWITCH_TURN
+            }
+        };
+
+        let mut best: Option<(i32, Vec<usize>)> = None;
+        for order in permutation_list(slot_list.len()) {
+            let total: i32 = order
+                .iter()
+                .enumerate()
+                .map(|(hero_id, &i)| cost(hero_id, &slot_list[i]))
+                .sum();
+            if best.as_ref().is_none_or(|(best_total, _)| total < *best_total) {
+                best = Some((total, order));
+            }
+        }
+        let order = best.map(|(_, order)| order).unwrap_or_default();
+        self.slot_list = order.iter().map(|&i| slot_list[i]).collect();
+        self.slot_list.clone()
+    }
+
+    pub fn slot(&self, hero_id: usize) -> Option<&Slot> {
+        self.slot_list.get(hero_id)
+    }
+
+    pub fn group_list(&self) -> Vec<RoleGroup> {
+        let mut ret: Vec<RoleGroup> = vec![];
+        for (hero_id, slot) in self.slot_list.iter().enumerate() {
+            let role = slot.role;
+            match ret
+                .iter_mut()
+                .find(|g| g.role == Role::PairAttack && role == Role::PairAttack)
+            {
+                Some(group) => group.hero_list.push(hero_id),
+                None => ret.push(RoleGroup {
+                    role,
+                    hero_list: vec![hero_id],
+                }),
+            }
+        }
+        ret
+    }
+
+    pub fn describe(&self) -> String {
+        format!("role: {:?}", self.group_list())
+    }
+}
+
+/// 0..n の並べ方全て
+fn permutation_list(n: usize) -> Vec<Vec<usize>> {
+    if n == 0 {
+        return vec![vec![]];
+    }
+    let mut ret = vec![];
+    for order in permutation_list(n - 1) {
+        for i in 0..n {
+            let mut order = order.clone();
+            order.insert(i, n - 1);
+            ret.push(order);
+        }
+    }
+    ret
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_assign() {
+        let slot = |role: Role, index: usize, x: i32| Slot {
+            role,
+            index,
+            point: Point { x, y: 0 },
+        };
+        let slot_list = [
+            slot(Role::PairAttack, 0, 16000),
+            slot(Role::PairAttack, 1, 16000),
+            slot(Role::Defender, 0, 0),
+        ];
+        let mut manager = RoleManager::default();
+        // 最初は順番通り
+        let hero_list = [Point { x: 0, y: 0 }, Point { x: 15000, y: 0 }, Point { x: 14000, y: 0 }];
+        assert_eq!(
+            manager.assign(&hero_list, &slot_list, &[false; 3])[0].role,
+            Role::PairAttack
+        );
+        assert_eq!(
+            manager.assign(&hero_list, &slot_list, &[false; 3])[0].role,
+            Role::Defender
+        );
+        assert_eq!(
+            manager.group_list(),
+            vec![
+                RoleGroup {
+                    role: Role::Defender,
+                    hero_list: vec![0]
+                },
+                RoleGroup {
+                    role: Role::PairAttack,
+                    hero_list: vec![1, 2]
+                },
+            ]
+        );
+
+        // 少し近くなったくらいでは入れ替えない
+        let hero_list = [
+            Point { x: 1600, y: 0 },
+            Point { x: 15000, y: 0 },
+            Point { x: 1000, y: 0 },
+        ];
+        assert_eq!(
+            manager.assign(&hero_list, &slot_list, &[false; 3])[0].role,
+            Role::Defender
+        );
+        // 大きく離れれば入れ替えるが、monster を追っている最中なら入れ替えない
+        let hero_list = [
+            Point { x: 9000, y: 0 },
+            Point { x: 15000, y: 0 },
+            Point { x: 6000, y: 0 },
+        ];
+        assert_eq!(
+            manager.assign(&hero_list, &slot_list, &[true, false, false])[0].role,
+            Role::Defender
+        );
+        assert_eq!(
+            manager.assign(&hero_list, &slot_list, &[false; 3])[0].role,
+            Role::PairAttack
+        );
+    }
+}
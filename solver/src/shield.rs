@@ -0,0 +1,99 @@
+//! shield をかける時期
+//!
+//! shield はかけた次のターンから SHIELD_EFFECTIVE_TURN ターンの間、spell を受け付けなくなる
+//! 相手陣に向かう monster は、base に届くまでを shield が覆える所まで来てから、
+//! その間に相手 hero の spell が届きそうで、殴り倒されない時だけ包む (早すぎると届く前に切れる)
+//! 自分の hero は、spell を撃てるマナのある相手の hero が 2200 以内にいる時だけ包む
+
+use simulator::inout::*;
+use simulator::{CONTROL_EFFECTIVE_RADIUS, MANA_TO_SPELL, MAX_HERO_VELOCITY, SHIELD_EFFECTIVE_TURN};
+
+use crate::geometry::MonsterPath;
+
+/// 相手陣に向かう m を今 shield で包むべきか
+pub fn monster_needs_shield(board: &Board, m: &Monster, opponent_mana: i32) -> bool {
+    if m.shield_life > 0 || opponent_mana < MANA_TO_SPELL {
+        return false;
+    }
+    let path = MonsterPath::toward(board, m, &board.opponent.base);
+    // 今ターンにかけた shield は、届くターンまで切れない
+    let arrival = match path.arrival {
+        Some(arrival) if arrival <= SHIELD_EFFECTIVE_TURN as usize + 1 => arrival,
+        _ => return false,
+    };
+    let opponent_hero_list = board.opponent.hero_list.iter().map(|h| h.pos).collect::<Vec<_>>();
+    if path.kill_turn(m.health, &opponent_hero_list).is_some() {
+        return false;
+    }
+    // t ターン後の spell は、相手 hero が t 回動いてから撃てる
+    board.opponent.hero_list.iter().any(|h| {
+        (1..arrival).any(|t| {
+            h.pos
+                .in_range(&path.path[t], MAX_HERO_VELOCITY * t as i32 + CONTROL_EFFECTIVE_RADIUS)
+        })
+    })
+}
+
+/// 自分の hero を今 shield で包むべきか
+pub fn hero_needs_shield(board: &Board, hero: &Hero, opponent_mana: i32) -> bool {
+    hero.shield_life == 0
+        && opponent_mana >= MANA_TO_SPELL
+        && board
+            .opponent
+            .hero_list
+            .iter()
+            .any(|h| h.pos.in_range(&hero.pos, CONTROL_EFFECTIVE_RADIUS))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use simulator::{MAX_X, MAX_Y};
+
+    #[test]
+    fn test_needs_shield() {
+        let mut board = Board {
+            player: Player::new(),
+            opponent: Player::new(),
+            monster_list: vec![],
+            turn: 100,
+        };
+        let base = Point { x: MAX_X, y: MAX_Y };
+        board.opponent.base = base;
+        let opponent_hero = Hero {
+            id: 3,
+            pos: base - Point { x: 1500, y: 1500 },
+            shield_life: 0,
+            is_controlled: false,
+        };
+        board.opponent.hero_list.push(opponent_hero.clone());
+
+        // base に真っ直ぐ向かう monster (x 方向に 400 ずつ)
+        let monster = |distance: i32, health: i32| Monster {
+            id: 10,
+            pos: base - Point { x: distance, y: 0 },
+            shield_life: 0,
+            is_controlled: false,
+            health,
+            v: Point { x: 400, y: 0 },
+            threat_state: MonsterThreatState::OpponentThreat,
+        };
+        // 届くまで 13ターンなら今かければ最後まで覆えるが、14ターンだと早すぎる
+        assert!(monster_needs_shield(&board, &monster(300 + 400 * 13, 40), 100));
+        assert!(!monster_needs_shield(&board, &monster(300 + 400 * 14, 40), 100));
+        // 相手に spell を撃つマナが無い時と、どうせ倒される時は要らない
+        assert!(!monster_needs_shield(&board, &monster(300 + 400 * 13, 40), 0));
+        assert!(!monster_needs_shield(&board, &monster(300 + 400 * 13, 6), 100));
+
+        let mut hero = Hero {
+            id: 0,
+            pos: opponent_hero.pos - Point { x: 2000, y: 0 },
+            shield_life: 0,
+            is_controlled: false,
+        };
+        assert!(hero_needs_shield(&board, &hero, 100));
+        assert!(!hero_needs_shield(&board, &hero, 0));
+        hero.pos = opponent_hero.pos - Point { x: 3000, y: 0 };
+        assert!(!hero_needs_shield(&board, &hero, 100));
+    }
+}
@@ -0,0 +1,147 @@
+//! 1ターンの持ち時間の管理
+//!
+//! 最初のターンは 1000ms、それ以降は 50ms で、超えると負けになる
+//! solver の処理を段階 (Phase) に分けて、それぞれ持ち時間の何割まで使って良いかを決める
+//! 段階の終わりで時間を見て、超えていたら重い処理を飛ばして安全な action で返す
+
+// wasm32-unknown-unknown では std::time::Instant が使えないので、ブラウザの時計を使う
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Instant;
+#[cfg(target_arch = "wasm32")]
+use web_time::Instant;
+
+pub const FIRST_TURN_LIMIT: u128 = 1000;
+pub const TURN_LIMIT: u128 = 50;
+// 入出力や計測の誤差の分として、持ち時間から引いておく [ms]
+const SAFETY_MARGIN: u128 = 10;
+
+/// solve の中の段階
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Phase {
+    /// 盤面の記憶の更新、防衛の割り当て、マナ集めの計画
+    Prepare,
+    /// role 毎の判断 (2回)
+    Role,
+    /// beam search, MCTS
+    Search,
+}
+
+impl Phase {
+    /// 使える時間のうち、この段階の終わりまでに使って良い割合 [%]
+    fn percent(self) -> u128 {
+        match self {
+            Phase::Prepare => 30,
+            Phase::Role => 50,
+            Phase::Search => 100,
+        }
+    }
+}
+
+/// ターンの持ち時間 [ms]
+pub fn turn_limit(turn: usize) -> u128 {
+    if turn <= 1 {
+        FIRST_TURN_LIMIT
+    } else {
+        TURN_LIMIT
+    }
+}
+
+/// phase が終わっているべき、ターン開始からの時間 [ms]
+pub fn deadline(turn: usize, phase: Phase) -> u128 {
+    (turn_limit(turn) - SAFETY_MARGIN) * phase.percent() / 100
+}
+
+/// 1ターン分の時計
+#[derive(Clone, Copy, Debug)]
+pub struct TurnTimer {
+    start: Instant,
+    turn: usize,
+}
+
+impl TurnTimer {
+    pub fn start(turn: usize) -> TurnTimer {
+        TurnTimer {
+            start: Instant::now(),
+            turn,
+        }
+    }
+
+    /// ターン開始からの時間 [ms]
+    pub fn elapsed(&self) -> u128 {
+        (Instant::now() - self.start).as_millis()
+    }
+
+    /// phase に割り当てた時間を使い切ったか
+    pub fn is_over(&self, phase: Phase) -> bool {
+        self.elapsed() >= deadline(self.turn, phase)
+    }
+}
+
+/// 持ち時間を超えそうになった回数の記録
+#[derive(Clone, Debug, Default)]
+pub struct TimeManager {
+    /// 段階の途中で時間を使い切って、処理を飛ばした回数
+    pub skip_count: usize,
+    /// 持ち時間そのものを超えた回数 (対戦サーバーなら負けている)
+    pub overrun_count: usize,
+    pub max_elapsed: u128,
+}
+
+impl TimeManager {
+    /// phase を飛ばしたことを覚えておく
+    ///
+    /// log に出すかは呼ぶ側が決めるので、出す文字列を返す
+    pub fn record_skip(&mut self, turn: usize, phase: Phase, elapsed: u128) -> String {
+        self.skip_count += 1;
+        format!(
+            "time: skipped after {:?} at turn {} ({} / {} [ms])",
+            phase,
+            turn,
+            elapsed,
+            deadline(turn, phase)
+        )
+    }
+
+    /// solve の最後に呼ぶ。持ち時間を超えていたら log に出す文字列を返す
+    pub fn record_turn(&mut self, turn: usize, elapsed: u128) -> Option<String> {
+        self.max_elapsed = self.max_elapsed.max(elapsed);
+        if elapsed < turn_limit(turn) {
+            return None;
+        }
+        self.overrun_count += 1;
+        Some(format!(
+            "time: OVERRUN at turn {} ({} / {} [ms])",
+            turn,
+            elapsed,
+            turn_limit(turn)
+        ))
+    }
+
+    pub fn describe(&self) -> String {
+        format!(
+            "time: max {}[ms], skip {}, overrun {}",
+            self.max_elapsed, self.skip_count, self.overrun_count
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_deadline() {
+        // 最初のターンだけ長く使える
+        assert_eq!(deadline(1, Phase::Search), 990);
+        assert_eq!(deadline(2, Phase::Search), 40);
+        assert!(deadline(2, Phase::Prepare) < deadline(2, Phase::Role));
+
+        let mut manager = TimeManager::default();
+        manager.record_turn(1, 300);
+        manager.record_turn(2, 49);
+        assert_eq!(manager.overrun_count, 0);
+        assert!(manager.record_turn(3, 50).is_some());
+        assert_eq!(manager.overrun_count, 1);
+        assert_eq!(manager.max_elapsed, 300);
+    }
+}
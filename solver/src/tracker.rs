@@ -0,0 +1,234 @@
+//! 視界の外に出たものの記憶
+//!
+//! monster は誰にも触られなければ直進するので、見えなくなってからも位置を進め続ける
+//! 相手 hero は最後に見た位置と、そこからの経過ターンだけ覚えておく
+
+use simulator::inout::*;
+use simulator::{
+    Simulator, BASE_ATTACK_RADIUS, BASE_ATTRACTION_RADIUS, MAX_HERO_VELOCITY, MAX_MONSTER_VELOCITY, MAX_X, MAX_Y,
+    VISIBLE_RADIUS_FROM_BASE, VISIBLE_RADIUS_FROM_HERO,
+};
+
+use crate::forward::Belief;
+
+#[derive(Clone, Debug)]
+pub struct TrackedMonster {
+    /// 見えていない間は、推測した位置・threat_state に更新し続ける
+    pub monster: Monster,
+    pub last_seen_turn: usize,
+}
+
+impl TrackedMonster {
+    pub fn age(&self, turn: usize) -> usize {
+        turn - self.last_seen_turn
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct TrackedHero {
+    /// 最後に見た時の状態
+    pub hero: Hero,
+    pub last_seen_turn: usize,
+}
+
+impl TrackedHero {
+    pub fn age(&self, turn: usize) -> usize {
+        turn - self.last_seen_turn
+    }
+
+    /// 今いるかもしれない範囲の半径
+    pub fn uncertainty(&self, turn: usize) -> i32 {
+        self.age(turn) as i32 * MAX_HERO_VELOCITY
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Tracker {
+    monster_list: Vec<TrackedMonster>,
+    opponent_hero_list: Vec<TrackedHero>,
+}
+
+impl Tracker {
+    /// 毎ターン、solve の最初に呼ぶ
+    pub fn update(&mut self, board: &Board) {
+        let visible = |p: &Point| {
+            board.player.base.in_range(p, VISIBLE_RADIUS_FROM_BASE)
+                || board
+                    .player
+                    .hero_list
+                    .iter()
+                    .any(|h| h.pos.in_range(p, VISIBLE_RADIUS_FROM_HERO))
+        };
+
+        // 見えていないものは1ターン分進める
+        // 見えるはずの場所にいないなら、倒されたか wind で飛ばされたので忘れる
+        self.monster_list.retain_mut(|tracked| {
+            if board.monster(tracked.monster.id).is_some() {
+                return true;
+            }
+            let m = &mut tracked.monster;
+            m.pos = m.next_pos();
+            if let Some(base) = [board.player.base, board.opponent.base]
+                .iter()
+                .find(|base| base.in_range(&m.pos, BASE_ATTRACTION_RADIUS))
+            {
+                if base.in_range(&m.pos, BASE_ATTACK_RADIUS) {
+                    return false;
+                }
+                m.v = ((*base - m.pos).to_f64().normalize() * MAX_MONSTER_VELOCITY as f64).to::<i32>();
+            }
+            m.threat_state = threat_state(board, m);
+            m.is_controlled = false;
+            m.shield_life = (m.shield_life - 1).max(0);
+            Simulator::is_in_gameboard(&m.pos) && !visible(&m.pos)
+        });
+
+        for m in board.monster_list.iter() {
+            let tracked = TrackedMonster {
+                monster: m.clone(),
+                last_seen_turn: board.turn,
+            };
+            match self.monster_list.iter_mut().find(|t| t.monster.id == m.id) {
+                Some(t) => *t = tracked,
+                None => self.monster_list.push(tracked),
+            }
+        }
+
+        for h in board.opponent.hero_list.iter() {
+            let tracked = TrackedHero {
+                hero: h.clone(),
+                last_seen_turn: board.turn,
+            };
+            match self.opponent_hero_list.iter_mut().find(|t| t.hero.id == h.id) {
+                Some(t) => *t = tracked,
+                None => self.opponent_hero_list.push(tracked),
+            }
+        }
+    }
+
+    /// 今は見えていない monster
+    pub fn unseen_monster_list<'a>(&'a self, board: &'a Board) -> impl Iterator<Item = &'a TrackedMonster> {
+        self.monster_list
+            .iter()
+            .filter(|t| board.monster(t.monster.id).is_none())
+    }
+
+    /// 今は見えていない相手 hero
+    pub fn unseen_opponent_hero_list<'a>(&'a self, board: &'a Board) -> impl Iterator<Item = &'a TrackedHero> {
+        self.opponent_hero_list
+            .iter()
+            .filter(|t| board.opponent.hero_list.iter().all(|h| h.id != t.hero.id))
+    }
+
+    /// 見えていない monster のうち、自陣に一番早く入ってくるものと、入ってくる位置・ターン数
+    pub fn incoming_monster<'a>(&'a self, board: &'a Board) -> Option<(&'a TrackedMonster, Point, i32)> {
+        self.unseen_monster_list(board)
+            .filter(|t| t.monster.threat_state == MonsterThreatState::PlayerThreatInTheFuture)
+            .filter_map(|t| {
+                let m = &t.monster;
+                (1..(MAX_X + MAX_Y) / MAX_MONSTER_VELOCITY)
+                    .map(|turn| (m.pos + m.v * turn, turn))
+                    .find(|(p, _)| board.player.base.in_range(p, BASE_ATTRACTION_RADIUS))
+                    .map(|(p, turn)| (t, p, turn))
+            })
+            .min_by_key(|(_, _, turn)| *turn)
+    }
+
+    /// forward model に渡す推測
+    pub fn belief(&self, board: &Board) -> Belief {
+        Belief {
+            monster_list: self.unseen_monster_list(board).map(|t| t.monster.clone()).collect(),
+            opponent_hero_list: self.unseen_opponent_hero_list(board).map(|t| t.hero.clone()).collect(),
+        }
+    }
+}
+
+/// Simulator::decide_monster_threat と同じ分類を board 上で行う
+fn threat_state(board: &Board, m: &Monster) -> MonsterThreatState {
+    if m.v.norm2() == 0 {
+        return MonsterThreatState::NotThreat;
+    }
+    for turn in 0.. {
+        let p = m.pos + m.v * turn;
+        if board.player.base.in_range(&p, VISIBLE_RADIUS_FROM_BASE) {
+            return if turn == 0 {
+                MonsterThreatState::PlayerThreat
+            } else {
+                MonsterThreatState::PlayerThreatInTheFuture
+            };
+        } else if board.opponent.base.in_range(&p, VISIBLE_RADIUS_FROM_BASE) {
+            return if turn == 0 {
+                MonsterThreatState::OpponentThreat
+            } else {
+                MonsterThreatState::OpponentThreatInTheFuture
+            };
+        } else if turn > 0 && !(0 <= p.x && p.x < MAX_X && 0 <= p.y && p.y < MAX_Y) {
+            // 盤面の外にいるかは、1ターン進めた所から見る (湧いたばかりの monster は盤面の外にいる)
+            return MonsterThreatState::NotThreat;
+        }
+    }
+    unreachable!()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_track_unseen_monster() {
+        let mut sim = Simulator::new(3);
+        let mut tracker = Tracker::default();
+        for _ in 0..60 {
+            // 中央で見張って、通り過ぎた monster を追わせる
+            let center = (0..3)
+                .map(|_| Action::Move {
+                    point: simulator::CENTER,
+                    message: String::new(),
+                })
+                .collect();
+            let wait = (0..3).map(|_| Action::Wait { message: String::new() }).collect();
+            sim.next_state(center, wait);
+            let board = sim.to_board(0);
+            tracker.update(&board);
+
+            // 誰にも触られない monster は、見えなくなっても本当の位置を追える
+            for t in tracker.unseen_monster_list(&board) {
+                if let Some(m) = sim
+                    .components
+                    .monster_list
+                    .iter()
+                    .find(|m| m.component.id == t.monster.id)
+                {
+                    assert_eq!(m.component.position, t.monster.pos);
+                }
+            }
+        }
+        assert!(tracker.unseen_monster_list(&sim.to_board(0)).count() > 0);
+    }
+
+    #[test]
+    fn test_threat_state() {
+        let mut board = Board {
+            player: Player::new(),
+            opponent: Player::new(),
+            monster_list: vec![],
+            turn: 100,
+        };
+        board.opponent.base = Point { x: MAX_X, y: MAX_Y };
+        let monster = |pos: Point, v: Point| Monster {
+            id: 10,
+            pos,
+            shield_life: 0,
+            is_controlled: false,
+            health: 20,
+            v,
+            threat_state: MonsterThreatState::NotThreat,
+        };
+        // 盤面の外にいても、次のターンに盤面に入って自陣に向かうなら脅威になる
+        let m = monster(Point { x: 7000, y: -100 }, Point { x: -400, y: 100 });
+        assert_eq!(threat_state(&board, &m), MonsterThreatState::PlayerThreatInTheFuture);
+        // 盤面の外に出ていく monster は脅威にならない
+        let m = monster(Point { x: 7000, y: 100 }, Point { x: 0, y: -400 });
+        assert_eq!(threat_state(&board, &m), MonsterThreatState::NotThreat);
+    }
+}
@@ -0,0 +1,349 @@
+//! WIND を続けて撃って、monster を相手の base に押し込む計画
+//!
+//! wind は範囲内の monster を 2200 押し、押されたターンの monster は自分では動かない
+//! 撃った hero はその場に残るので、1体だと追いついてまた撃つまで数ターンかかるが、
+//! 2体なら交互に撃ったり、同じターンに重ねて 4400 押したりできる
+//! 各ターンに hero 毎に「撃つ」か「追いかける」かを全て試して、base の 300 以内に入れられる計画を探す
+//!
+//! 成功率は、入るまでに殴られて残る health と、相手 hero の spell が届く所を通る回数から見積もる
+
+use simulator::inout::*;
+use simulator::{
+    BASE_ATTACK_RADIUS, BASE_ATTRACTION_RADIUS, CONTROL_EFFECTIVE_RADIUS, HERO_ATTACK_RADIUS, MANA_GAIN_TO_ATTACK,
+    MANA_TO_SPELL, MAX_HERO_VELOCITY, MAX_MONSTER_VELOCITY, WIND_DISTANCE, WIND_EFFECTIVE_RADIUS,
+};
+
+// 何ターン先まで計画するか
+pub const PLAN_TURN: usize = 6;
+// wind を撃つのは、monster に近い hero 2体まで
+const MAX_HERO_COUNT: usize = 2;
+// 追いかける hero は、次のターンに wind が届き、殴らずに済む距離で待つ
+const STANDOFF_DISTANCE: i32 = WIND_EFFECTIVE_RADIUS - 200;
+// 相手 hero の spell が届く所を 1回通る度に、押し返される確率
+const COUNTER_PROBABILITY: f64 = 0.3;
+// base に入った時にこれだけ health が残っていれば、殴られる見積もりが外れても大丈夫
+const HEALTH_MARGIN: i32 = 3 * MANA_GAIN_TO_ATTACK;
+
+/// hero_id の hero が turn ターン後 (0 なら今ターン) に撃つ wind
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WindStep {
+    pub turn: usize,
+    pub hero_id: usize,
+    /// wind の向き (monster から base に向かう向きに押すための点)
+    pub point: Point,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct WindPlan {
+    pub monster_id: i32,
+    pub wind_list: Vec<WindStep>,
+    /// 今ターンに wind を撃たない hero が向かう位置
+    pub move_list: Vec<(usize, Point)>,
+    /// base に入るターン (1 なら今ターンの終わり)
+    pub arrival: usize,
+    pub mana: i32,
+    /// base に入った時の health の見込み
+    pub health: i32,
+    pub success_probability: f64,
+}
+
+impl WindPlan {
+    /// hero_id の hero が今ターンにすること
+    pub fn action(&self, hero_id: usize) -> Option<Action> {
+        if let Some(step) = self.wind_list.iter().find(|s| s.turn == 0 && s.hero_id == hero_id) {
+            return Some(Action::Wind {
+                point: step.point,
+                message: format!("[wc]wind {}", self.monster_id),
+            });
+        }
+        self.move_list
+            .iter()
+            .find(|(id, _)| *id == hero_id)
+            .map(|(_, point)| Action::Move {
+                point: *point,
+                message: format!("[wc]chase {}", self.monster_id),
+            })
+    }
+
+    pub fn describe(&self) -> String {
+        format!(
+            "wind chain: monster {}, {} wind(s), arrival {}, health {}, success {:.2}",
+            self.monster_id,
+            self.wind_list.len(),
+            self.arrival,
+            self.health,
+            self.success_probability
+        )
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Node {
+    pos: Point,
+    v: Point,
+    health: i32,
+    shield_life: i32,
+    hero_list: Vec<(usize, Point)>,
+    mana: i32,
+    // 相手 hero の spell が届く所を通った回数
+    exposure: i32,
+    wind_list: Vec<WindStep>,
+    move_list: Vec<(usize, Point)>,
+}
+
+/// hero_id_list の hero で monster を押し込む計画のうち、一番成功しそうなもの
+///
+/// 使えるマナは mana まで、相手は opponent_mana の分だけ spell で邪魔できるものとする
+/// 同じ成功率なら早く入る方、使うマナが少ない方を選ぶ
+pub fn plan(board: &Board, m: &Monster, hero_id_list: &[usize], mana: i32, opponent_mana: i32) -> Option<WindPlan> {
+    let mut hero_list = hero_id_list
+        .iter()
+        .map(|&id| (id, board.player.hero_list[id].pos))
+        .collect::<Vec<_>>();
+    hero_list.sort_by_key(|(_, p)| p.distance(&m.pos));
+    hero_list.truncate(MAX_HERO_COUNT);
+    let root = Node {
+        pos: m.pos,
+        v: m.v,
+        health: m.health,
+        shield_life: m.shield_life,
+        hero_list,
+        mana,
+        exposure: 0,
+        wind_list: vec![],
+        move_list: vec![],
+    };
+    let mut best = None;
+    search(board, m.id, opponent_mana, 0, root, &mut best);
+    // wind を撃たなくても入るものは、押す必要が無い
+    best.filter(|p: &WindPlan| !p.wind_list.is_empty())
+}
+
+/// 見えている全ての monster について plan を立てて、一番成功しそうなもの
+pub fn best_plan(board: &Board, hero_id_list: &[usize], mana: i32, opponent_mana: i32) -> Option<WindPlan> {
+    if hero_id_list.is_empty() || mana < MANA_TO_SPELL {
+        return None;
+    }
+    board
+        .monster_list
+        .iter()
+        // 最初の wind までに届かないほど遠い monster は見ない
+        .filter(|m| {
+            hero_id_list.iter().any(|&id| {
+                board.player.hero_list[id].pos.distance(&m.pos)
+                    <= MAX_HERO_VELOCITY * PLAN_TURN as i32 + WIND_EFFECTIVE_RADIUS
+            })
+        })
+        .filter_map(|m| plan(board, m, hero_id_list, mana, opponent_mana))
+        .fold(
+            None,
+            |best, p| if is_better(&p, best.as_ref()) { Some(p) } else { best },
+        )
+}
+
+/// plan が best より良い計画か (best が無ければ良い)
+pub fn is_better(plan: &WindPlan, best: Option<&WindPlan>) -> bool {
+    let key = |p: &WindPlan| (p.success_probability, -(p.arrival as i32), -p.mana);
+    match best {
+        Some(best) => key(plan) > key(best),
+        None => true,
+    }
+}
+
+fn search(board: &Board, monster_id: i32, opponent_mana: i32, turn: usize, node: Node, best: &mut Option<WindPlan>) {
+    if turn == PLAN_TURN {
+        return;
+    }
+    let base = board.opponent.base;
+    // 今ターン wind を撃てる hero
+    let ready = if node.shield_life == 0 {
+        node.hero_list
+            .iter()
+            .enumerate()
+            .filter(|(_, (_, p))| p.in_range(&node.pos, WIND_EFFECTIVE_RADIUS))
+            .map(|(i, _)| i)
+            .collect::<Vec<_>>()
+    } else {
+        vec![]
+    };
+
+    // 撃つ hero の組を全て試す (撃たない組も含む)
+    for mask in 0..1usize << ready.len() {
+        let wind_index_list = ready
+            .iter()
+            .enumerate()
+            .filter(|(bit, _)| mask >> bit & 1 == 1)
+            .map(|(_, &i)| i)
+            .collect::<Vec<_>>();
+        let cost = MANA_TO_SPELL * wind_index_list.len() as i32;
+        if cost > node.mana {
+            continue;
+        }
+        let mut next = step(board, &node, turn, &wind_index_list, opponent_mana);
+        next.mana -= cost;
+        if next.health <= 0 {
+            continue;
+        }
+        if base.in_range(&next.pos, BASE_ATTACK_RADIUS) {
+            let plan = WindPlan {
+                monster_id,
+                mana: MANA_TO_SPELL * next.wind_list.len() as i32,
+                wind_list: next.wind_list,
+                move_list: next.move_list,
+                arrival: turn + 1,
+                health: next.health,
+                success_probability: success_probability(next.health, next.exposure, opponent_mana),
+            };
+            if is_better(&plan, best.as_ref()) {
+                *best = Some(plan);
+            }
+            continue;
+        }
+        search(board, monster_id, opponent_mana, turn + 1, next, best);
+    }
+}
+
+/// node から 1ターン進める (wind_index_list の hero が撃ち、残りは追いかける)
+fn step(board: &Board, node: &Node, turn: usize, wind_index_list: &[usize], opponent_mana: i32) -> Node {
+    let base = board.opponent.base;
+    let mut next = node.clone();
+
+    // 押された monster は自分では動かない
+    let push = if wind_index_list.is_empty() {
+        None
+    } else {
+        let dir = ((base - node.pos).to_f64().normalize() * WIND_DISTANCE as f64).to::<i32>();
+        Some(dir * wind_index_list.len() as i32)
+    };
+    let moved = match push {
+        Some(push) => node.pos + push,
+        None => node.pos + node.v,
+    };
+
+    for (i, (hero_id, hero)) in node.hero_list.iter().enumerate() {
+        if wind_index_list.contains(&i) {
+            // monster から base に向かう向きに押す
+            next.wind_list.push(WindStep {
+                turn,
+                hero_id: *hero_id,
+                point: *hero + (base - node.pos),
+            });
+            continue;
+        }
+        let point = chase_point(hero, &moved);
+        if turn == 0 {
+            next.move_list.push((*hero_id, point));
+        }
+        next.hero_list[i].1 = point;
+        // 近づきすぎると殴ってしまう (hero は monster が動く前に殴る)
+        if point.in_range(&node.pos, HERO_ATTACK_RADIUS) {
+            next.health -= MANA_GAIN_TO_ATTACK;
+        }
+    }
+
+    // 相手 hero は、追いつけるなら殴ってくるし、spell が届くなら押し返してくる
+    for h in board.opponent.hero_list.iter() {
+        let distance = h.pos.distance(&node.pos);
+        if distance <= MAX_HERO_VELOCITY * (turn as i32 + 1) + HERO_ATTACK_RADIUS {
+            next.health -= MANA_GAIN_TO_ATTACK;
+        }
+        if node.shield_life == 0
+            && opponent_mana >= MANA_TO_SPELL
+            && distance <= MAX_HERO_VELOCITY * turn as i32 + CONTROL_EFFECTIVE_RADIUS
+        {
+            next.exposure += 1;
+        }
+    }
+
+    next.pos = moved;
+    if base.in_range(&moved, BASE_ATTRACTION_RADIUS) {
+        next.v = ((base - moved).to_f64().normalize() * MAX_MONSTER_VELOCITY as f64).to::<i32>();
+    }
+    next.shield_life = (node.shield_life - 1).max(0);
+    next
+}
+
+/// target から STANDOFF_DISTANCE 離れた所に向かう (1ターンで行ける所まで)
+fn chase_point(hero: &Point, target: &Point) -> Point {
+    let goal = if hero.in_range(target, STANDOFF_DISTANCE) {
+        *hero
+    } else {
+        *target + ((*hero - *target).to_f64().normalize() * STANDOFF_DISTANCE as f64).to::<i32>()
+    };
+    if hero.in_range(&goal, MAX_HERO_VELOCITY) {
+        goal
+    } else {
+        *hero + ((goal - *hero).to_f64().normalize() * MAX_HERO_VELOCITY as f64).to::<i32>()
+    }
+}
+
+/// 押し返しに使える spell の分だけ、押し返されなかった確率を掛ける
+fn success_probability(health: i32, exposure: i32, opponent_mana: i32) -> f64 {
+    let counter = exposure.min(opponent_mana / MANA_TO_SPELL);
+    let health_factor = (health as f64 / HEALTH_MARGIN as f64).min(1.0);
+    (1.0 - COUNTER_PROBABILITY).powi(counter) * health_factor
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use simulator::{MAX_X, MAX_Y, SHIELD_EFFECTIVE_TURN};
+
+    #[test]
+    fn test_plan() {
+        let mut board = Board {
+            player: Player::new(),
+            opponent: Player::new(),
+            monster_list: vec![],
+            turn: 100,
+        };
+        let base = Point { x: MAX_X, y: MAX_Y };
+        board.opponent.base = base;
+        // 相手の base から 4500 の所で止まっている monster と、その手前の hero 2体
+        let m = Monster {
+            id: 10,
+            pos: base - Point { x: 3600, y: 2700 },
+            shield_life: 0,
+            is_controlled: false,
+            health: 20,
+            v: Point { x: 0, y: 0 },
+            threat_state: MonsterThreatState::NotThreat,
+        };
+        board.player.hero_list = (0..3)
+            .map(|id| Hero {
+                id,
+                pos: m.pos - Point { x: 400, y: 300 * id },
+                shield_life: 0,
+                is_controlled: false,
+            })
+            .collect();
+
+        // 2体で同じターンに撃てば 4400 押して入る
+        let double = plan(&board, &m, &[0, 1], 100, 0).unwrap();
+        assert_eq!(double.arrival, 1);
+        assert_eq!(double.mana, 20);
+        assert_eq!(double.success_probability, 1.0);
+        assert!(matches!(double.action(0), Some(Action::Wind { .. })));
+
+        // マナが 1回分しか無ければ、押してから歩かせるので遅くなる
+        let single = plan(&board, &m, &[0, 1], 10, 0).unwrap();
+        assert_eq!(single.mana, 10);
+        assert!(single.arrival > 1);
+
+        // 相手 hero が近くにいると、押し返されるかもしれない
+        board.opponent.hero_list.push(Hero {
+            id: 3,
+            pos: base - Point { x: 2400, y: 1800 },
+            shield_life: 0,
+            is_controlled: false,
+        });
+        let defended = plan(&board, &m, &[0, 1], 100, 50).unwrap();
+        assert!(defended.success_probability < 1.0);
+        // shield がかかっていると、切れるまで押せない
+        let shielded = Monster {
+            shield_life: SHIELD_EFFECTIVE_TURN,
+            ..m.clone()
+        };
+        assert_eq!(plan(&board, &shielded, &[0, 1], 100, 50), None);
+    }
+}
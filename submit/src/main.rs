@@ -1061,8 +1061,12 @@ struct DefenderInfo {
 
 impl DefenderInfo {
     fn new() -> DefenderInfo {
+        let distance = (PARAMS.defender_distance as f64 / std::f64::consts::SQRT_2) as i32;
         DefenderInfo {
-            home: IPoint { y: 3000, x: 3000 },
+            home: IPoint {
+                y: distance,
+                x: distance,
+            },
         }
     }
 
@@ -1236,7 +1240,7 @@ impl SolverState {
         if has_priority {
             board.player.mana - self.spell_count * 10 >= 10
         } else {
-            board.player.mana - (2 + self.spell_count) * 10 >= 10
+            board.player.mana - (PARAMS.spell_reserve + self.spell_count) * 10 >= 10
         }
     }
 }
@@ -1271,6 +1275,23 @@ const WIND_ATTACK_MARGIN: i32 = 0;
 const FIRST_WIND_ATTACK_THREASHOLD: i32 = THREASHOLD_BASE_DAMAGE_RADIUS + 3 * WIND_DISTANCE + WIND_ATTACK_MARGIN;
 const SECOND_WIND_ATTACK_THREASHOLD: i32 = THREASHOLD_BASE_DAMAGE_RADIUS + 2 * WIND_DISTANCE + WIND_ATTACK_MARGIN;
 
+// BAKE_BEGIN: solver の bake_params で生成 (手で書き換えない)
+#[derive(Clone, Copy, Debug)]
+struct Params {
+    attack_mana: i32,
+    spell_reserve: i32,
+    defender_distance: i32,
+    wind_detect_distance: i32,
+}
+
+const PARAMS: Params = Params {
+    attack_mana: 150,
+    spell_reserve: 2,
+    defender_distance: 4243,
+    wind_detect_distance: 1180,
+};
+// BAKE_END
+
 impl Solver {
     fn new(base_pos: &IPoint, hero_size: usize) -> Solver {
         Solver {
@@ -1323,7 +1344,7 @@ impl Solver {
         for (hero_id, hero) in board.player.hero_list.iter().enumerate() {
             // WIND を使われているかは、直前の場所との距離で判断
             if hero.is_controlled
-                || !self.solver_state.previous_position[hero_id].in_range(&hero.pos, WIND_RADIUS - 100)
+                || !self.solver_state.previous_position[hero_id].in_range(&hero.pos, PARAMS.wind_detect_distance)
             {
                 self.solver_state.is_opponent_speller[hero_id] = true;
             }
@@ -1361,7 +1382,7 @@ impl Solver {
         }
 
         // 相手に比べてマナがたくさんある || 十分マナが揃ったら攻撃態勢
-        if !self.solver_state.strategy_changed && board.player.mana >= 150 {
+        if !self.solver_state.strategy_changed && board.player.mana >= PARAMS.attack_mana {
             self.solver_state.strategy_changed = true;
             // 防御だけ残しておく
             self.hero_state.retain(|g| g.hero_list[0] == 2);
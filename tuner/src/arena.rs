@@ -0,0 +1,79 @@
+//! パラメータ同士を simulator の上で戦わせる
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+use simulator::{Action, Simulator};
+use solver::{Params, Solver};
+
+/// seed の試合を player 0 を params_list[0]、player 1 を params_list[1] で最後まで進める
+///
+/// player 0 から見て、勝ち 1, 引き分け 0.5, 負け 0
+pub fn play(seed: u64, params_list: [Params; 2]) -> f64 {
+    let mut solver_list = params_list.map(|params| {
+        let mut solver = Solver::with_params(&simulator::IPoint::new(), 3, params);
+        solver.disable_log();
+        solver
+    });
+    let mut sim = Simulator::new(seed);
+    while !sim.finish_game() {
+        let player1_action = solve(&mut solver_list[0], &sim, 0);
+        let player2_action = solve(&mut solver_list[1], &sim, 1);
+        sim.next_state(player1_action, player2_action);
+    }
+
+    // 220ターン終わった時は health、それも同じなら相手陣で稼いだマナが多い方の勝ち
+    let [player1, player2] = &sim.components.player_list;
+    let key = |p: &simulator::Player| (p.health, p.wild_mana);
+    match key(player1).cmp(&key(player2)) {
+        std::cmp::Ordering::Greater => 1.0,
+        std::cmp::Ordering::Equal => 0.5,
+        std::cmp::Ordering::Less => 0.0,
+    }
+}
+
+fn solve(solver: &mut Solver, sim: &Simulator, player_id: usize) -> Vec<Action> {
+    let mut board = sim.to_board(player_id as i32);
+    if player_id == 0 {
+        return solver.solve(&board);
+    }
+    // simulater 側で player2 は点対称に回して渡すので、action を戻す
+    board.point_symmetry();
+    solver
+        .solve(&board)
+        .into_iter()
+        .map(|action| action.point_symmetry())
+        .collect()
+}
+
+/// candidate を champion と seed_list の全ての seed で、先手と後手を入れ替えて戦わせた時の平均の score
+///
+/// thread_count 本の thread で並列に試合をする
+pub fn evaluate(candidate: Params, champion: Params, seed_list: &[u64], thread_count: usize) -> f64 {
+    if seed_list.is_empty() {
+        return 0.5;
+    }
+    // (seed, candidate が先手か) の組を、空いた thread から順に取っていく
+    let game_list = seed_list
+        .iter()
+        .flat_map(|&seed| [(seed, true), (seed, false)])
+        .collect::<Vec<_>>();
+    let next = AtomicUsize::new(0);
+    let total = Mutex::new(0.0);
+    std::thread::scope(|scope| {
+        for _ in 0..thread_count.clamp(1, game_list.len()) {
+            scope.spawn(|| {
+                while let Some(&(seed, first)) = game_list.get(next.fetch_add(1, Ordering::Relaxed)) {
+                    let score = if first {
+                        play(seed, [candidate, champion])
+                    } else {
+                        1.0 - play(seed, [champion, candidate])
+                    };
+                    *total.lock().unwrap() += score;
+                }
+            });
+        }
+    });
+    let total = total.into_inner().unwrap();
+    total / game_list.len() as f64
+}
@@ -0,0 +1,43 @@
+//! 途中経過の保存と再開
+//!
+//! 1反復毎に JSON で書き出すので、止めても同じ file を指定すれば続きから回せる
+
+use serde::{Deserialize, Serialize};
+use solver::Params;
+
+use crate::spsa::Spsa;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Checkpoint {
+    pub spsa: Spsa,
+    /// 戦わせる相手 (最後まで変えない)
+    pub champion: Params,
+    /// 検証用の seed で一番 score が高かった組
+    pub best: Params,
+    pub best_score: f64,
+}
+
+impl Checkpoint {
+    /// file が無ければ None
+    pub fn load(path: &str) -> Result<Option<Checkpoint>, String> {
+        if !std::path::Path::new(path).exists() {
+            return Ok(None);
+        }
+        let text = std::fs::read_to_string(path).map_err(|e| format!("failed to open {}: {}", path, e))?;
+        serde_json::from_str(&text)
+            .map(Some)
+            .map_err(|e| format!("failed to parse {}: {}", path, e))
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        let text = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        write_atomic(path, &text)
+    }
+}
+
+/// 書いている途中で止められても壊れないように、別の file に書いてから置き換える
+pub fn write_atomic(path: &str, text: &str) -> Result<(), String> {
+    let tmp = format!("{}.tmp", path);
+    std::fs::write(&tmp, text).map_err(|e| format!("failed to write {}: {}", tmp, e))?;
+    std::fs::rename(&tmp, path).map_err(|e| format!("failed to write {}: {}", path, e))
+}
@@ -0,0 +1,175 @@
+//! solver のパラメータを、ローカルの simulator での対戦で調整する
+//!
+//! 候補のパラメータを固定の champion と多数の seed で (先手後手を入れ替えて) 並列に戦わせ、SPSA で勝率を上げていく
+//! 途中経過は checkpoint に書き出し、同じ checkpoint を指定すれば続きから回る
+//! 検証用の seed で一番勝率が高かったパラメータを、solver の Params::load で読める TOML で書き出す
+//!
+//! ```text
+//! cargo run -p tuner --release -- [options]
+//!   --champion <path>     戦わせる相手のパラメータ (TOML か JSON、default: 既定値)
+//!   --init <path>         調整を始めるパラメータ (default: champion)
+//!   --iterations <n>      反復の回数 (default: 1000)
+//!   --seeds <n>           1反復で候補毎に使う seed の数 (先手後手で 2試合ずつ、default: 16)
+//!   --validation <n>      検証に使う seed の数 (default: 64)
+//!   --eval-interval <n>   何反復毎に検証するか (default: 10)
+//!   --threads <n>         並列に試合をする thread の数 (default: CPU の数)
+//!   --step <a>            SPSA の進む量 (default: 0.02)
+//!   --perturbation <c>    SPSA のずらす量 (既定値の大きさに対する割合、default: 0.1)
+//!   --checkpoint <path>   途中経過の JSON (default: tuner_checkpoint.json)
+//!   --out <path>          一番良かったパラメータの TOML (default: best_params.toml)
+//! ```
+//!
+//! submit に埋め込むには、solver の bake_params に --out の file を渡す
+
+mod arena;
+mod checkpoint;
+mod spsa;
+
+use solver::Params;
+
+use checkpoint::Checkpoint;
+use spsa::{Gain, Spsa};
+
+// 検証用の seed は 0 から、反復で使う seed はここから順に使う (同じ seed に合わせ込まないように、反復毎に変える)
+const TRAINING_SEED_OFFSET: u64 = 1_000_000;
+// 摂動の向きを決める乱数の seed
+const RANDOM_SEED: u64 = 0x5eed;
+
+struct Options {
+    champion: Option<String>,
+    init: Option<String>,
+    iterations: usize,
+    seeds: usize,
+    validation: usize,
+    eval_interval: usize,
+    threads: usize,
+    gain: Gain,
+    checkpoint: String,
+    out: String,
+}
+
+impl Options {
+    fn parse(args: Vec<String>) -> Result<Options, String> {
+        let mut ret = Options {
+            champion: None,
+            init: None,
+            iterations: 1000,
+            seeds: 16,
+            validation: 64,
+            eval_interval: 10,
+            threads: std::thread::available_parallelism().map_or(1, |n| n.get()),
+            gain: Gain::default(),
+            checkpoint: "tuner_checkpoint.json".to_string(),
+            out: "best_params.toml".to_string(),
+        };
+
+        let mut args = args.into_iter();
+        while let Some(key) = args.next() {
+            let value = args.next().ok_or(format!("missing value for `{}`", key))?;
+            let invalid = || format!("invalid value for `{}`: `{}`", key, value);
+            match key.as_str() {
+                "--champion" => ret.champion = Some(value),
+                "--init" => ret.init = Some(value),
+                "--iterations" => ret.iterations = value.parse().map_err(|_| invalid())?,
+                "--seeds" => ret.seeds = value.parse().map_err(|_| invalid())?,
+                "--validation" => ret.validation = value.parse().map_err(|_| invalid())?,
+                "--eval-interval" => ret.eval_interval = value.parse().map_err(|_| invalid())?,
+                "--threads" => ret.threads = value.parse().map_err(|_| invalid())?,
+                "--step" => ret.gain.step = value.parse().map_err(|_| invalid())?,
+                "--perturbation" => ret.gain.perturbation = value.parse().map_err(|_| invalid())?,
+                "--checkpoint" => ret.checkpoint = value,
+                "--out" => ret.out = value,
+                _ => return Err(format!("unknown option `{}`", key)),
+            }
+        }
+        if ret.eval_interval == 0 {
+            return Err("`--eval-interval` must be positive".to_string());
+        }
+        Ok(ret)
+    }
+}
+
+fn main() {
+    if let Err(e) = run(std::env::args().skip(1).collect()) {
+        eprintln!("error: {}", e);
+        std::process::exit(1);
+    }
+}
+
+fn run(args: Vec<String>) -> Result<(), String> {
+    let options = Options::parse(args)?;
+    let validation_seed_list = (0..options.validation as u64).collect::<Vec<_>>();
+
+    let mut checkpoint = match Checkpoint::load(&options.checkpoint)? {
+        Some(checkpoint) => {
+            println!(
+                "resume from {} (iteration {}, best score {:.3})",
+                options.checkpoint, checkpoint.spsa.iteration, checkpoint.best_score
+            );
+            checkpoint
+        }
+        None => {
+            let champion = match &options.champion {
+                Some(path) => Params::load(path)?,
+                None => Params::default(),
+            };
+            let init = match &options.init {
+                Some(path) => Params::load(path)?,
+                None => champion,
+            };
+            let best_score = arena::evaluate(init, champion, &validation_seed_list, options.threads);
+            println!("initial score: {:.3}", best_score);
+            let checkpoint = Checkpoint {
+                spsa: Spsa::new(&init, options.gain, RANDOM_SEED),
+                champion,
+                best: init,
+                best_score,
+            };
+            write_best(&options, &checkpoint)?;
+            checkpoint
+        }
+    };
+
+    while checkpoint.spsa.iteration < options.iterations {
+        let iteration = checkpoint.spsa.iteration;
+        let seed_list = (0..options.seeds)
+            .map(|i| TRAINING_SEED_OFFSET + (iteration * options.seeds + i) as u64)
+            .collect::<Vec<_>>();
+        let perturbation = checkpoint.spsa.perturb();
+        let plus_score = arena::evaluate(perturbation.plus, checkpoint.champion, &seed_list, options.threads);
+        let minus_score = arena::evaluate(perturbation.minus, checkpoint.champion, &seed_list, options.threads);
+        checkpoint.spsa.update(&perturbation, plus_score, minus_score);
+        println!(
+            "iteration {}: plus {:.3}, minus {:.3}",
+            iteration + 1,
+            plus_score,
+            minus_score
+        );
+
+        if checkpoint.spsa.iteration % options.eval_interval == 0 || checkpoint.spsa.iteration == options.iterations {
+            let current = checkpoint.spsa.current();
+            let score = arena::evaluate(current, checkpoint.champion, &validation_seed_list, options.threads);
+            println!("  validation score: {:.3} (best {:.3})", score, checkpoint.best_score);
+            if score > checkpoint.best_score {
+                checkpoint.best = current;
+                checkpoint.best_score = score;
+                write_best(&options, &checkpoint)?;
+            }
+            for (name, value) in current.field_list() {
+                println!("  {} = {}", name, value);
+            }
+        }
+        checkpoint.save(&options.checkpoint)?;
+    }
+    Ok(())
+}
+
+fn write_best(options: &Options, checkpoint: &Checkpoint) -> Result<(), String> {
+    let header = format!(
+        "# tuner: iteration {}, score {:.3} against the champion on {} seeds\n",
+        checkpoint.spsa.iteration, checkpoint.best_score, options.validation
+    );
+    checkpoint::write_atomic(&options.out, &(header + &checkpoint.best.to_toml()))?;
+    println!("  wrote {}", options.out);
+    Ok(())
+}
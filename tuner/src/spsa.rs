@@ -0,0 +1,174 @@
+//! SPSA (simultaneous perturbation stochastic approximation)
+//!
+//! 全てのパラメータを同時に ±c_k だけランダムな向きにずらした 2つの組を戦わせ、その score の差から勾配を推定して a_k だけ進む
+//! 試合の結果はばらつくが、評価は 1反復あたり 2回で済むので、パラメータの数が多くても回せる
+//!
+//! パラメータは既定値の大きさで割って、どれも 1 くらいの大きさとして扱う
+
+use serde::{Deserialize, Serialize};
+use solver::Params;
+
+/// 反復 k の進む量 a_k = step / (k + 1 + stability)^0.602 とずらす量 c_k = perturbation / (k + 1)^0.101
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Gain {
+    pub step: f64,
+    pub perturbation: f64,
+    pub stability: f64,
+}
+
+impl Default for Gain {
+    fn default() -> Self {
+        Gain {
+            step: 0.02,
+            perturbation: 0.1,
+            stability: 10.0,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Spsa {
+    pub gain: Gain,
+    pub iteration: usize,
+    // 正規化した値 (値 / scale)
+    theta: Vec<f64>,
+    scale: Vec<f64>,
+    rng: u64,
+}
+
+/// 1反復で戦わせる 2つの組
+#[derive(Clone, Debug)]
+pub struct Perturbation {
+    pub plus: Params,
+    pub minus: Params,
+    // plus と minus の、正規化した値の差
+    diff: Vec<f64>,
+}
+
+impl Spsa {
+    pub fn new(init: &Params, gain: Gain, seed: u64) -> Spsa {
+        let field_list = init.field_list();
+        Spsa {
+            gain,
+            iteration: 0,
+            theta: field_list
+                .iter()
+                .map(|&(name, value)| value as f64 / scale(name))
+                .collect(),
+            scale: field_list.iter().map(|&(name, _)| scale(name)).collect(),
+            // 0 だと xorshift が回らない
+            rng: seed.max(1),
+        }
+    }
+
+    /// 今の推定値
+    pub fn current(&self) -> Params {
+        self.to_params(|i| (self.theta[i] * self.scale[i]).round() as i32)
+    }
+
+    /// 今の推定値を、全てのパラメータで同時に ±c_k ずらす
+    ///
+    /// 整数に丸めても同じ値にならないように、最低でも 1 はずらす
+    pub fn perturb(&mut self) -> Perturbation {
+        let c = self.gain.perturbation / ((self.iteration + 1) as f64).powf(0.101);
+        let current = self.current().field_list();
+        let mut value_list = [vec![], vec![]];
+        for (i, &(name, value)) in current.iter().enumerate() {
+            let sign = if self.next_random() & 1 == 0 { 1 } else { -1 };
+            let delta = ((c * self.scale[i]).round() as i32).max(1) * sign;
+            value_list[0].push(clamp(name, value + delta));
+            value_list[1].push(clamp(name, value - delta));
+        }
+        Perturbation {
+            plus: self.to_params(|i| value_list[0][i]),
+            minus: self.to_params(|i| value_list[1][i]),
+            diff: (0..self.theta.len())
+                .map(|i| (value_list[0][i] - value_list[1][i]) as f64 / self.scale[i])
+                .collect(),
+        }
+    }
+
+    /// plus, minus の score から勾配を推定して、score が上がる向きに進む
+    pub fn update(&mut self, perturbation: &Perturbation, plus_score: f64, minus_score: f64) {
+        let a = self.gain.step / (self.iteration as f64 + 1.0 + self.gain.stability).powf(0.602);
+        let name_list = self.current().field_list();
+        for (i, &(name, _)) in name_list.iter().enumerate() {
+            // 範囲の端で動けなかったパラメータは、勾配が分からない
+            if perturbation.diff[i] == 0.0 {
+                continue;
+            }
+            let gradient = (plus_score - minus_score) / perturbation.diff[i];
+            let (min, max) = range(name);
+            self.theta[i] =
+                (self.theta[i] + a * gradient).clamp(min as f64 / self.scale[i], max as f64 / self.scale[i]);
+        }
+        self.iteration += 1;
+    }
+
+    fn to_params(&self, value: impl Fn(usize) -> i32) -> Params {
+        let mut ret = Params::default();
+        for (i, (name, _)) in Params::default().field_list().into_iter().enumerate() {
+            *ret.field_mut(name).unwrap() = clamp(name, value(i));
+        }
+        ret
+    }
+
+    // xorshift64
+    fn next_random(&mut self) -> u64 {
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 7;
+        self.rng ^= self.rng << 17;
+        self.rng
+    }
+}
+
+/// 既定値の大きさ (小さいものは 1)
+fn scale(name: &str) -> f64 {
+    let default = Params::default();
+    default
+        .field_list()
+        .iter()
+        .find(|&&(n, _)| n == name)
+        .map_or(1.0, |&(_, value)| (value.abs() as f64).max(1.0))
+}
+
+/// パラメータの取れる範囲: どれも負にはならず、割合 [%] は 100 まで
+fn range(name: &str) -> (i32, i32) {
+    if name.ends_with("_percent") {
+        (0, 100)
+    } else {
+        (0, i32::MAX)
+    }
+}
+
+fn clamp(name: &str, value: i32) -> i32 {
+    let (min, max) = range(name);
+    value.clamp(min, max)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_climb() {
+        // attack_mana が 150 に近いほど良い、という score で 150 に寄っていく
+        let init = Params::default();
+        let score = |params: &Params| -((params.attack_mana - 150) as f64).abs() / 100.0;
+        let mut spsa = Spsa::new(
+            &init,
+            Gain {
+                step: 0.5,
+                ..Gain::default()
+            },
+            1,
+        );
+        for _ in 0..200 {
+            let perturbation = spsa.perturb();
+            assert!(perturbation.plus.farmer0_home_percent <= 100);
+            let (plus, minus) = (score(&perturbation.plus), score(&perturbation.minus));
+            spsa.update(&perturbation, plus, minus);
+        }
+        assert!((spsa.current().attack_mana - 150).abs() < (init.attack_mana - 150).abs() / 2);
+    }
+}
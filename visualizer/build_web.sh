@@ -0,0 +1,28 @@
+#!/bin/bash
+# visualizer を wasm にビルドして、docs/ に index.html と一緒に置く
+# 必要なもの:
+#   rustup target add wasm32-unknown-unknown
+#   cargo install wasm-bindgen-cli
+set -eu
+
+script_path=$( cd "$(dirname "${BASH_SOURCE[0]}")" ; pwd -P )
+cd "$script_path"
+
+CRATE_NAME="visualizer"
+
+# clipboard 等を使うのに必要
+export RUSTFLAGS=--cfg=web_sys_unstable_apis
+
+rm -f "docs/${CRATE_NAME}_bg.wasm" "docs/${CRATE_NAME}.js"
+
+echo "Building rust…"
+cargo build -p ${CRATE_NAME} --release --lib --target wasm32-unknown-unknown
+
+# workspace の target directory を使う
+TARGET=$(cargo metadata --format-version=1 --no-deps | sed -n 's/.*"target_directory":"\([^"]*\)".*/\1/p')
+
+echo "Generating JS bindings for wasm…"
+wasm-bindgen "${TARGET}/wasm32-unknown-unknown/release/${CRATE_NAME}.wasm" \
+  --out-dir docs --no-modules --no-typescript
+
+echo "Finished: docs/${CRATE_NAME}_bg.wasm"
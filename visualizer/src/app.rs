@@ -1,32 +1,227 @@
-use crate::app::egui::Pos2;
-use crate::app::egui::Stroke;
-use eframe::egui::Painter;
+use std::cell::RefCell;
+
 use eframe::egui::RichText;
 use eframe::epaint::FontId;
-use eframe::{egui, epaint::Color32, epi};
+use eframe::{egui, epi};
+
+use render::game::{self, TurnLog};
+use simulator::replay::Replay;
+use solver::Annotation;
 
-use simulator::IPoint;
-use simulator::CENTER;
-use simulator::MAP_LIMIT;
-use simulator::MAX_X;
-use simulator::MAX_Y;
+use crate::scene;
+
+thread_local! {
+    // web 版で、ページ側のファイル選択から渡された replay
+    static PENDING_REPLAY: RefCell<Option<String>> = RefCell::new(None);
+}
 
+/// 次の update で replay として読み込ませる
+#[cfg(target_arch = "wasm32")]
+pub fn push_pending_replay(text: String) {
+    PENDING_REPLAY.with(|pending| *pending.borrow_mut() = Some(text));
+}
+
+/// 再起動しても引き継ぐ表示の設定
+///
+/// 試合の状態 (Simulator, Solver) は保存せず、起動時に seed か replay から作り直す
+/// panel の幅や CollapsingHeader の開閉は egui 側の memory に保存される
 /// We derive Deserialize/Serialize so we can persist app state on shutdown.
 #[cfg_attr(feature = "persistence", derive(serde::Deserialize, serde::Serialize))]
 #[cfg_attr(feature = "persistence", serde(default))] // if we add new fields, give them default values when deserializing old state
+struct ViewSettings {
+    // solver の判断材料を map 上に描画するか
+    show_annotation: [bool; 2],
+    show_info_panel: bool,
+    // 自動再生 (turn / 秒)
+    play_speed: f32,
+    // new game で使う seed
+    seed: u64,
+    replay_path: String,
+    // 最後に開いた replay (native のみ、起動時に開き直す)
+    last_replay: Option<String>,
+}
+
+impl Default for ViewSettings {
+    fn default() -> Self {
+        Self {
+            show_annotation: [true, false],
+            show_info_panel: true,
+            play_speed: 5.0,
+            seed: 0,
+            replay_path: "replay.txt".to_string(),
+            last_replay: None,
+        }
+    }
+}
+
 pub struct TemplateApp {
+    settings: ViewSettings,
+
+    seed: u64,
     sim: simulator::Simulator,
     solver1: solver::Solver,
     solver2: solver::Solver,
+
+    // turn 毎の記録
+    // 最後の要素は sim.turn のもので、まだ sim には適用していない
+    turn_log_list: Vec<TurnLog>,
+    selected_turn: usize,
+    // selected_turn が最新でない時に、seed から再計算した盤面
+    selected_sim: Option<simulator::Simulator>,
+
+    // replay を再生している時は、solver を動かさずに記録された action を使う
+    is_replay: bool,
+    playing: bool,
+    play_timer: f32,
+
+    status: String,
 }
 
 impl Default for TemplateApp {
     fn default() -> Self {
+        let mut solver1 = solver::Solver::new(&simulator::IPoint::new(), 3);
+        // simulater 側で solver2 の方は点対称に回して渡すので、IPoint(x: MAX_X, y: MAX_Y) ではない
+        let mut solver2 = solver::Solver::new(&simulator::IPoint::new(), 3);
+        solver1.enable_annotation();
+        solver2.enable_annotation();
+
         Self {
+            settings: ViewSettings::default(),
+            seed: 0,
             sim: simulator::Simulator::new(0),
-            solver1: solver::Solver::new(&simulator::IPoint::new(), 3),
-            // simulater 側で solver2 の方は点対称に回して渡すので、IPoint(x: MAX_X, y: MAX_Y) ではない
-            solver2: solver::Solver::new(&simulator::IPoint::new(), 3),
+            solver1,
+            solver2,
+            turn_log_list: vec![],
+            selected_turn: 0,
+            selected_sim: None,
+            is_replay: false,
+            playing: false,
+            play_timer: 0.0,
+            status: String::new(),
+        }
+    }
+}
+
+impl TemplateApp {
+    /// 最新ターンの action を solver に決めさせて、記録する
+    fn solve_latest_turn(&mut self) {
+        let (player1_action, player1_annotation) = game::solve(&mut self.solver1, &self.sim, 0);
+        let (player2_action, player2_annotation) = game::solve(&mut self.solver2, &self.sim, 1);
+
+        self.turn_log_list.push(TurnLog {
+            action_list: [player1_action, player2_action],
+            annotation_list: [player1_annotation, player2_annotation],
+        });
+    }
+
+    fn new_game(&mut self, seed: u64) {
+        *self = Self {
+            settings: ViewSettings {
+                seed,
+                last_replay: None,
+                ..std::mem::take(&mut self.settings)
+            },
+            seed,
+            sim: simulator::Simulator::new(seed),
+            ..Self::default()
+        };
+    }
+
+    /// 保存されていた設定から、前回の replay か seed で試合を始め直す
+    #[cfg(feature = "persistence")]
+    fn restore_game(&mut self) {
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(path) = self.settings.last_replay.clone() {
+            self.open_replay_file(&path);
+            if self.is_replay {
+                return;
+            }
+        }
+        self.new_game(self.settings.seed);
+    }
+
+    pub fn load_replay(&mut self, text: &str) {
+        match game::parse_replay(text) {
+            Ok(replay) => {
+                self.new_game(replay.seed);
+                self.is_replay = true;
+                self.turn_log_list = replay
+                    .turn_list
+                    .into_iter()
+                    .map(|action_list| TurnLog {
+                        action_list,
+                        annotation_list: [vec![], vec![]],
+                    })
+                    .collect();
+                self.status = format!("loaded replay: {} turns", self.turn_log_list.len());
+            }
+            Err(e) => self.status = format!("failed to load replay: {}", e),
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn open_replay_file(&mut self, path: &str) {
+        match std::fs::read_to_string(path) {
+            Ok(text) => {
+                self.load_replay(&text);
+                if self.is_replay {
+                    self.settings.replay_path = path.to_string();
+                    self.settings.last_replay = Some(path.to_string());
+                }
+            }
+            Err(e) => self.status = format!("failed to open {}: {}", path, e),
+        }
+    }
+
+    /// sim に適用済みの action を replay として保存する
+    #[cfg(not(target_arch = "wasm32"))]
+    fn save_replay_file(&mut self) {
+        let mut replay = Replay::new(self.seed);
+        for log in self.turn_log_list[..self.sim.turn].iter() {
+            replay.push(log.action_list[0].clone(), log.action_list[1].clone());
+        }
+        let path = &self.settings.replay_path;
+        self.status = match std::fs::write(path, game::replay_to_string(&replay, path)) {
+            Ok(()) => format!("saved {}", path),
+            Err(e) => format!("failed to save {}: {}", path, e),
+        };
+    }
+
+    fn can_go_next(&self) -> bool {
+        self.sim.turn < self.turn_log_list.len() && !self.sim.finish_game()
+    }
+
+    fn next_turn(&mut self) {
+        let log = &self.turn_log_list[self.sim.turn];
+        self.sim
+            .next_state(log.action_list[0].clone(), log.action_list[1].clone());
+        if !self.is_replay {
+            self.solve_latest_turn();
+        }
+        self.select_turn(self.sim.turn);
+    }
+
+    fn select_turn(&mut self, turn: usize) {
+        self.selected_turn = turn;
+        let selected_sim = if turn == self.sim.turn {
+            None
+        } else {
+            // Simulator は seed と action 列から再現できる
+            let mut sim = simulator::Simulator::new(self.seed);
+            for log in self.turn_log_list[..turn].iter() {
+                sim.next_state(log.action_list[0].clone(), log.action_list[1].clone());
+            }
+            Some(sim)
+        };
+
+        if self.is_replay {
+            // replay は先の turn も分かっているので、盤面ごと移動する
+            if let Some(sim) = selected_sim {
+                self.sim = sim;
+            }
+            self.selected_sim = None;
+        } else {
+            self.selected_sim = selected_sim;
         }
     }
 }
@@ -42,7 +237,18 @@ impl epi::App for TemplateApp {
         // Note that you must enable the `persistence` feature for this to work.
         #[cfg(feature = "persistence")]
         if let Some(storage) = _storage {
-            *self = epi::get_value(storage, epi::APP_KEY).unwrap_or_default()
+            let settings: ViewSettings = epi::get_value(storage, epi::APP_KEY).unwrap_or_default();
+            if self.is_replay {
+                // 起動時の引数で開いた replay を優先する
+                self.settings = ViewSettings {
+                    replay_path: std::mem::take(&mut self.settings.replay_path),
+                    last_replay: self.settings.last_replay.take(),
+                    ..settings
+                };
+            } else {
+                self.settings = settings;
+                self.restore_game();
+            }
         }
     }
 
@@ -50,216 +256,212 @@ impl epi::App for TemplateApp {
     /// Note that you must enable the `persistence` feature for this to work.
     #[cfg(feature = "persistence")]
     fn save(&mut self, storage: &mut dyn epi::Storage) {
-        epi::set_value(storage, epi::APP_KEY, self);
+        epi::set_value(storage, epi::APP_KEY, &self.settings);
     }
 
     /// Called each time the UI needs repainting, which may be many times per second.
     /// Put your widgets into a `SidePanel`, `TopPanel`, `CentralPanel`, `Window` or `Area`.
-    fn update(&mut self, ctx: &egui::Context, frame: &epi::Frame) {
+    fn update(&mut self, ctx: &egui::Context, _frame: &epi::Frame) {
         // setup fonts
 
+        // ページ側のファイル選択・ドラッグ&ドロップで渡された replay
+        if let Some(text) = PENDING_REPLAY.with(|pending| pending.borrow_mut().take()) {
+            self.load_replay(&text);
+        }
+        let dropped_files = ctx.input().raw.dropped_files.clone();
+        for file in dropped_files {
+            if let Some(bytes) = file.bytes {
+                self.load_replay(&String::from_utf8_lossy(&bytes));
+            } else if let Some(_path) = file.path {
+                #[cfg(not(target_arch = "wasm32"))]
+                self.open_replay_file(&_path.to_string_lossy());
+            }
+        }
+
         // setup simulator
-        let Self { sim, solver1, solver2 } = self;
-
-        let scale = (simulator::MAX_X as f32) / 1080.0;
-        let offset = simulator::MAP_LIMIT as f32 / scale + 50.0;
-
-        let convert = |v| v / scale + offset;
-
-        let draw_line = |painter: &Painter, p1: &IPoint, p2: &IPoint, color: Color32| {
-            let pos1 = Pos2 {
-                y: convert(p1.y as f32),
-                x: convert(p1.x as f32),
-            };
-            let pos2 = Pos2 {
-                y: convert(p2.y as f32),
-                x: convert(p2.x as f32),
-            };
-            painter.line_segment([pos1, pos2], Stroke { width: 2.0, color });
-        };
+        if !self.is_replay && self.turn_log_list.len() <= self.sim.turn {
+            self.solve_latest_turn();
+        }
 
-        let draw_circle = |painter: &Painter, circle: &IPoint, radius: f32, color: Color32| {
-            let center = Pos2 {
-                y: convert(circle.y as f32),
-                x: convert(circle.x as f32),
-            };
-            painter.circle(center, radius, Color32::WHITE, Stroke { width: 5.0, color });
-        };
+        // 自動再生
+        if self.playing {
+            self.play_timer += ctx.input().unstable_dt;
+            if self.play_timer * self.settings.play_speed >= 1.0 {
+                self.play_timer = 0.0;
+                if self.can_go_next() {
+                    self.next_turn();
+                } else {
+                    self.playing = false;
+                }
+            }
+            ctx.request_repaint();
+        }
 
-        egui::CentralPanel::default().show(ctx, |ui| {
-            let painter = ui.painter();
+        let mut selected_turn = self.selected_turn;
+        let mut next_turn_clicked = false;
+        let mut new_game_clicked = false;
+        #[cfg(not(target_arch = "wasm32"))]
+        let mut open_clicked = false;
+        #[cfg(not(target_arch = "wasm32"))]
+        let mut save_clicked = false;
+        let can_go_next = self.can_go_next();
 
-            // 外枠
-            let points = [
-                IPoint { x: 0, y: 0 },
-                IPoint { x: MAX_X, y: 0 },
-                IPoint { x: MAX_X, y: MAX_Y },
-                IPoint { x: 0, y: MAX_Y },
-            ];
+        {
+            let Self {
+                sim,
+                turn_log_list,
+                settings,
+                selected_sim,
+                is_replay,
+                playing,
+                status,
+                ..
+            } = self;
+            let sim = &*sim;
+            let turn_log_list = &*turn_log_list;
+            let shown = selected_sim.as_ref().unwrap_or(sim);
 
-            for i in 0..4 {
-                draw_line(painter, &points[i], &points[(i + 1) % 4], Color32::BLACK);
-            }
+            egui::CentralPanel::default().show(ctx, |ui| {
+                // solver の判断材料は、表示する player の分だけ描く
+                let annotation_list = match turn_log_list.get(shown.turn) {
+                    Some(log) => [0, 1].map(|player_id| {
+                        if settings.show_annotation[player_id] {
+                            &log.annotation_list[player_id][..]
+                        } else {
+                            &[]
+                        }
+                    }),
+                    None => [&[][..], &[][..]],
+                };
+                scene::paint(ui.painter(), &render::scene::map_shape_list(shown, annotation_list));
+            });
 
-            // 真の外枠
-            let points = [
-                IPoint {
-                    x: -MAP_LIMIT + 1,
-                    y: -MAP_LIMIT + 1,
-                },
-                IPoint {
-                    x: MAX_X + MAP_LIMIT - 1,
-                    y: -MAP_LIMIT + 1,
-                },
-                IPoint {
-                    x: MAX_X + MAP_LIMIT - 1,
-                    y: MAX_Y + MAP_LIMIT - 1,
-                },
-                IPoint {
-                    x: -MAP_LIMIT + 1,
-                    y: MAX_Y + MAP_LIMIT - 1,
-                },
-            ];
-            for i in 0..4 {
-                draw_line(painter, &points[i], &points[(i + 1) % 4], Color32::GRAY);
-            }
+            // let rich_text = |text: String| text;
+            let fontsize = 14.0;
+            let rich_text = |text: String| RichText::new(text).font(FontId::proportional(fontsize));
 
-            let spawn_list = [
-                IPoint {
-                    x: MAX_X / 2,
-                    y: -MAP_LIMIT + 1,
-                },
-                IPoint {
-                    x: MAX_X / 2 + 4000,
-                    y: -MAP_LIMIT + 1,
-                },
-                IPoint {
-                    x: MAX_X / 2,
-                    y: MAX_Y + MAP_LIMIT - 1,
-                },
-                IPoint {
-                    x: MAX_X / 2 - 4000,
-                    y: MAX_Y + MAP_LIMIT - 1,
-                },
-            ];
-            for point in spawn_list.iter() {
-                draw_circle(painter, point, 5.0, Color32::GRAY);
+            macro_rules! label {
+                ( $ui:ident, $name:expr, $e:expr ) => {
+                    $ui.label(rich_text(format!("{}: {:?}", $name, $e)));
+                };
             }
 
-            // hero
-            for (player, is_self) in sim.components.player_list.iter().zip([true, false]) {
-                let color = if is_self { Color32::RED } else { Color32::BLUE };
-                for hero in player.hero_list.iter() {
-                    // 本体
-                    draw_circle(painter, &hero.component.position, 5.0, color);
-                }
-            }
+            // turn 数と次の状態遷移
+            egui::TopBottomPanel::bottom("config").show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    if ui.button(rich_text("new game".to_string())).clicked() {
+                        new_game_clicked = true;
+                    }
+                    ui.add(egui::DragValue::new(&mut settings.seed).prefix("seed: "));
 
-            // monster
-            for monster in sim.components.monster_list.iter() {
-                draw_circle(painter, &monster.component.position, 5.0, Color32::BLACK);
-            }
-        });
+                    // button
+                    if ui
+                        .add_enabled(can_go_next, egui::Button::new(rich_text("next turn".to_string())))
+                        .clicked()
+                    {
+                        next_turn_clicked = true;
+                    }
+                    let play_text = if *playing { "pause" } else { "play" };
+                    if ui.button(rich_text(play_text.to_string())).clicked() {
+                        *playing = !*playing;
+                    }
+                    ui.add(egui::Slider::new(&mut settings.play_speed, 1.0..=30.0).text("turn/s"));
 
-        // let rich_text = |text: String| text;
-        let fontsize = 14.0;
-        let rich_text = |text: String| RichText::new(text).font(FontId::proportional(fontsize));
+                    ui.label(rich_text(format!("turn: {}", sim.turn)));
+                    let max_turn = if *is_replay { turn_log_list.len() } else { sim.turn };
+                    ui.add(egui::Slider::new(&mut selected_turn, 0..=max_turn).text("selected turn"));
+                });
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut settings.show_annotation[0], "player1 annotation");
+                    ui.checkbox(&mut settings.show_annotation[1], "player2 annotation");
+                    ui.checkbox(&mut settings.show_info_panel, "info panel");
 
-        macro_rules! label {
-            ( $ui:ident, $name:expr, $e:expr ) => {
-                $ui.label(rich_text(format!("{}: {:?}", $name, $e)));
-            };
-        }
+                    #[cfg(not(target_arch = "wasm32"))]
+                    {
+                        ui.text_edit_singleline(&mut settings.replay_path);
+                        open_clicked = ui.button(rich_text("open replay".to_string())).clicked();
+                        save_clicked = ui.button(rich_text("save replay".to_string())).clicked();
+                    }
 
-        // turn 数と次の状態遷移
-        egui::TopBottomPanel::bottom("config").show(ctx, |ui| {
-            // button
-            if ui.button(rich_text("next turn".to_string())).clicked() {
-                let player1_board = sim.to_board(0);
-                player1_board.dump();
-                let player1_action = solver1.solve(&player1_board);
-
-                let mut player2_board = sim.to_board(1);
-                player2_board.point_symmetry();
-                player2_board.dump();
-                let mut player2_action = solver2.solve(&player2_board);
-                // action 反転
-                player2_action = player2_action
-                    .into_iter()
-                    .map(|action| -> simulator::Action {
-                        match action {
-                            simulator::Action::Wait { message } => simulator::Action::Wait { message },
-                            simulator::Action::Move { point, message } => simulator::Action::Move {
-                                point: point.point_symmetry(&CENTER),
-                                message,
-                            },
-                            simulator::Action::Wind { point, message } => simulator::Action::Wind {
-                                point: point.point_symmetry(&CENTER),
-                                message,
-                            },
-                            simulator::Action::Shield { entity_id, message } => {
-                                simulator::Action::Shield { entity_id, message }
-                            }
-                            simulator::Action::Control {
-                                entity_id,
-                                point,
-                                message,
-                            } => simulator::Action::Control {
-                                entity_id,
-                                point: point.point_symmetry(&CENTER),
-                                message,
-                            },
-                        }
-                    })
-                    .collect();
+                    ui.label(rich_text(status.clone()));
+                });
+            });
 
-                sim.next_state(player1_action, player2_action);
-            }
-            ui.label(rich_text(format!("turn: {}", sim.turn)));
-        });
+            if settings.show_info_panel {
+                egui::SidePanel::right("info").min_width(300.0).show(ctx, |ui| {
+                    egui::ScrollArea::vertical().show(ui, |ui| {
+                        for (player_id, (text, player)) in ["player1".to_string(), "player2".to_string()]
+                            .iter()
+                            .zip(shown.components.player_list.iter())
+                            .enumerate()
+                        {
+                            // hero
+                            egui::CollapsingHeader::new(text).default_open(true).show(ui, |ui| {
+                                label!(ui, "health", player.health);
+                                label!(ui, "mana", player.mana);
+                                label!(ui, "base", player.base);
+                                for (hero_id, hero) in player.hero_list.iter().enumerate() {
+                                    egui::CollapsingHeader::new(format!("hero {}", hero_id))
+                                        .default_open(true)
+                                        .show(ui, |ui| {
+                                            label!(ui, "Action", hero.action);
+                                            label!(ui, "Position", hero.component.position);
+                                            label!(ui, "Velocity", hero.component.velocity);
+                                            label!(ui, "Id", hero.component.id);
+                                            label!(ui, "Shield_life", hero.component.shield_life);
+                                            label!(ui, "IsControled", hero.component.is_controlled());
+                                        });
+                                }
+                                if let Some(log) = turn_log_list.get(shown.turn) {
+                                    egui::CollapsingHeader::new(format!("{} solver state", text))
+                                        .default_open(false)
+                                        .show(ui, |ui| {
+                                            for annotation in log.annotation_list[player_id].iter() {
+                                                if let Annotation::Text { text } = annotation {
+                                                    ui.label(rich_text(text.clone()));
+                                                }
+                                            }
+                                        });
+                                }
+                            });
+                        }
 
-        egui::SidePanel::right("info").min_width(300.0).show(ctx, |ui| {
-            for (text, player) in ["player1".to_string(), "player2".to_string()]
-                .iter()
-                .zip(sim.components.player_list.iter())
-            {
-                // hero
-                egui::CollapsingHeader::new(text).default_open(true).show(ui, |ui| {
-                    label!(ui, "health", player.health);
-                    label!(ui, "mana", player.mana);
-                    label!(ui, "base", player.base);
-                    for (hero_id, hero) in player.hero_list.iter().enumerate() {
-                        egui::CollapsingHeader::new(format!("hero {}", hero_id))
+                        // monster
+                        egui::CollapsingHeader::new("monster list")
                             .default_open(true)
                             .show(ui, |ui| {
-                                label!(ui, "Action", hero.action);
-                                label!(ui, "Position", hero.component.position);
-                                label!(ui, "Velocity", hero.component.velocity);
-                                label!(ui, "Id", hero.component.id);
-                                label!(ui, "Shield_life", hero.component.shield_life);
-                                label!(ui, "IsControled", hero.component.is_controlled());
+                                for (monster_id, monster) in shown.components.monster_list.iter().enumerate() {
+                                    egui::CollapsingHeader::new(format!("monster {}", monster_id))
+                                        .default_open(true)
+                                        .show(ui, |ui| {
+                                            label!(ui, "Health", monster.health);
+                                            label!(ui, "Position", monster.component.position);
+                                            label!(ui, "Velocity", monster.component.velocity);
+                                            label!(ui, "Id", monster.component.id);
+                                            label!(ui, "Shield_life", monster.component.shield_life);
+                                            label!(ui, "IsControled", monster.component.is_controlled());
+                                        });
+                                }
                             });
-                    }
+                    });
                 });
             }
+        }
 
-            // monster
-            egui::CollapsingHeader::new("monster list")
-                .default_open(true)
-                .show(ui, |ui| {
-                    for (monster_id, monster) in sim.components.monster_list.iter().enumerate() {
-                        egui::CollapsingHeader::new(format!("monster {}", monster_id))
-                            .default_open(true)
-                            .show(ui, |ui| {
-                                label!(ui, "Health", monster.health);
-                                label!(ui, "Position", monster.component.position);
-                                label!(ui, "Velocity", monster.component.velocity);
-                                label!(ui, "Id", monster.component.id);
-                                label!(ui, "Shield_life", monster.component.shield_life);
-                                label!(ui, "IsControled", monster.component.is_controlled());
-                            });
-                    }
-                });
-        });
+        #[cfg(not(target_arch = "wasm32"))]
+        if open_clicked {
+            let path = self.settings.replay_path.clone();
+            self.open_replay_file(&path);
+        } else if save_clicked {
+            self.save_replay_file();
+        }
+
+        if new_game_clicked {
+            self.new_game(self.settings.seed);
+        } else if next_turn_clicked {
+            self.next_turn();
+        } else if selected_turn != self.selected_turn {
+            self.select_turn(selected_turn);
+        }
     }
 }
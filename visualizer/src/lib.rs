@@ -3,6 +3,7 @@
 #![warn(clippy::all, rust_2018_idioms)]
 
 mod app;
+pub mod scene;
 pub use app::TemplateApp;
 
 // ----------------------------------------------------------------------------
@@ -27,3 +28,10 @@ pub fn start(canvas_id: &str) -> Result<(), eframe::wasm_bindgen::JsValue> {
     let app = TemplateApp::default();
     eframe::start_web(canvas_id, Box::new(app))
 }
+
+/// ページ側のファイル選択で読み込んだ replay を app に渡す
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+pub fn load_replay(text: String) {
+    app::push_pending_replay(text);
+}
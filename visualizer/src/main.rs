@@ -8,7 +8,11 @@
 fn main() {
     use eframe::epaint::Vec2;
 
-    let app = visualizer::TemplateApp::default();
+    let mut app = visualizer::TemplateApp::default();
+    // 引数に replay のパスが渡されたら、それを開く
+    if let Some(path) = std::env::args().nth(1) {
+        app.open_replay_file(&path);
+    }
     let mut native_options = eframe::NativeOptions::default();
     native_options.initial_window_size = Some(Vec2 { x: 1600.0, y: 1200.0 });
     eframe::run_native(Box::new(app), native_options);
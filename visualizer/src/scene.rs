@@ -0,0 +1,45 @@
+//! render の描画要素を egui の painter で描く
+
+use eframe::egui::{Align2, Painter};
+use eframe::epaint::{Color32, FontId, Stroke};
+
+use render::scene::{Shape, FONT_SIZE};
+
+/// egui の painter で描く
+pub fn paint(painter: &Painter, shape_list: &[Shape]) {
+    for shape in shape_list.iter() {
+        match shape {
+            Shape::Line { from, to, width, color } => {
+                painter.line_segment(
+                    [*from, *to],
+                    Stroke {
+                        width: *width,
+                        color: *color,
+                    },
+                );
+            }
+            Shape::Circle {
+                center,
+                radius,
+                fill,
+                stroke,
+            } => {
+                painter.circle(
+                    *center,
+                    *radius,
+                    fill.unwrap_or(Color32::TRANSPARENT),
+                    stroke.unwrap_or(Stroke::none()),
+                );
+            }
+            Shape::Text { pos, text, color } => {
+                painter.text(
+                    *pos,
+                    Align2::CENTER_BOTTOM,
+                    text,
+                    FontId::proportional(FONT_SIZE),
+                    *color,
+                );
+            }
+        }
+    }
+}
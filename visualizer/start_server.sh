@@ -0,0 +1,10 @@
+#!/bin/bash
+# build_web.sh で作った docs/ を http://localhost:8080 で配信する
+set -eu
+
+script_path=$( cd "$(dirname "${BASH_SOURCE[0]}")" ; pwd -P )
+cd "$script_path/docs"
+
+PORT=${PORT:-8080}
+echo "open http://localhost:${PORT} in a browser"
+python3 -m http.server ${PORT} --bind 127.0.0.1